@@ -0,0 +1,140 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+use std::process::ExitStatus;
+use rustodo::Config;
+use rustodo::Variables;
+use commands::Settings;
+
+/// The add-on for an action like todo.sh finds it: an executable `ACTION` or
+/// `ACTION/ACTION` in the actions directory.
+pub fn find(config : &Config, action : &str) -> Option<PathBuf> {
+    if action.is_empty() || action.contains('/') || action.starts_with('.') {
+        return None;
+    }
+    let file = config.actions_dir.join(action);
+    [file.join(action), file].iter()
+        .find(|path| is_executable(path))
+        .cloned()
+}
+
+/// The actions of all add-ons, sorted.
+pub fn list(config : &Config) -> Vec<String> {
+    let mut actions = fs::read_dir(&config.actions_dir)
+        .map(|entries| entries
+            .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+            .filter(|action| find(config, action).is_some())
+            .collect::<Vec<String>>())
+        .unwrap_or_default();
+    actions.sort();
+    actions
+}
+
+/// Runs an add-on like todo.sh: with the action and its arguments, the standard streams
+/// of rustodo and the variables todo.sh exports, so `"$TODO_SH" ls` calls rustodo.
+pub fn run(settings : &Settings, path : &Path, action : &str, arguments : &[String]) -> io::Result<ExitStatus> {
+    Command::new(path)
+        .arg(action)
+        .args(arguments)
+        .envs(environment(settings))
+        .status()
+}
+
+/// The config variables and colors, overridden by the paths and flags in effect and the config file.
+fn environment(settings : &Settings) -> Variables {
+    let config = &settings.config;
+    let mut variables = settings.renderer.theme.to_variables();
+    variables.extend(config.variables.clone());
+
+    let program = env::current_exe().map(|path| path.display().to_string()).unwrap_or_else(|_| String::from("rustodo"));
+    let flag = |value : bool| String::from(if value { "1" } else { "0" });
+    let exported = [
+        ("TODO_SH", program.clone()),
+        ("TODO_FULL_SH", program),
+        ("TODO_DIR", config.todo_dir.display().to_string()),
+        ("TODO_FILE", config.todo_file.display().to_string()),
+        ("DONE_FILE", config.done_file.display().to_string()),
+        ("REPORT_FILE", config.report_file.display().to_string()),
+        ("TODO_ACTIONS_DIR", config.actions_dir.display().to_string()),
+        ("TODOTXT_FORCE", flag(settings.force)),
+        ("TODOTXT_AUTO_ARCHIVE", flag(settings.auto_archive)),
+        ("TODOTXT_PLAIN", flag(!settings.renderer.colored)),
+        ("TODOTXT_DATE_ON_ADD", flag(config.date_on_add)),
+        ("TODOTXT_PRESERVE_LINE_NUMBERS", flag(config.preserve_line_numbers)),
+        ("TODOTXT_VERBOSE", config.variable("TODOTXT_VERBOSE").unwrap_or("1").to_string()),
+    ];
+    for (name, value) in exported.iter() {
+        variables.insert(String::from(*name), value.clone());
+    }
+    //the config in effect, also with -d, so add-ons can source it or pass it on
+    if let Some(ref path) = config.path {
+        variables.insert(String::from("TODOTXT_CFG_FILE"), path.display().to_string());
+    }
+    variables
+}
+
+#[cfg(unix)]
+fn is_executable(path : &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path).is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path : &Path) -> bool {
+    path.is_file()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use addons::*;
+    use rustodo::Renderer;
+    use rustodo::Theme;
+    use std::os::unix::fs::PermissionsExt;
+    use std::process;
+
+    fn settings(name : &str) -> Settings {
+        let directory = env::temp_dir().join(format!("rustodo-addons-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(directory.join("actions/birdseye")).expect("Must create test directory.");
+        let mut config = Config::parse_shell("", None, &Variables::new());
+        config.todo_file = directory.join("todo.txt");
+        config.actions_dir = directory.join("actions");
+        Settings { config, force: true, auto_archive: false, renderer: Renderer::new(Theme::default(), false), piped_input: false }
+    }
+
+    fn write_script(path : &Path, content : &str, mode : u32) {
+        fs::write(path, content).expect("Must write the add-on.");
+        fs::set_permissions(path, fs::Permissions::from_mode(mode)).expect("Must set permissions.");
+    }
+
+    #[test]
+    fn finds_executable_files_and_directories() {
+        let settings = settings("find");
+        let actions = &settings.config.actions_dir;
+        write_script(&actions.join("lately"), "#!/bin/sh\n", 0o755);
+        write_script(&actions.join("notes"), "", 0o644);
+        write_script(&actions.join("birdseye/birdseye"), "#!/bin/sh\n", 0o755);
+
+        assert_eq!(find(&settings.config, "birdseye"), Some(actions.join("birdseye/birdseye")));
+        assert_eq!(find(&settings.config, "notes"), None);
+        assert_eq!(find(&settings.config, "../lately"), None);
+        assert_eq!(list(&settings.config), vec![String::from("birdseye"), String::from("lately")]);
+    }
+
+    #[test]
+    fn runs_add_ons_with_the_todo_sh_environment() {
+        let mut settings = settings("run");
+        settings.config.path = Some(settings.config.actions_dir.with_file_name("todo.cfg"));
+        let script = settings.config.actions_dir.join("lately");
+        let output = script.with_file_name("output");
+        write_script(&script, &format!("#!/bin/sh\nprintf '%s ' \"$1\" \"$2\" \"$TODO_FILE\" \"$TODOTXT_FORCE\" \"$PRI_A\" \"$TODOTXT_CFG_FILE\" > '{}'\n", output.display()), 0o755);
+
+        let status = run(&settings, &script, "lately", &[String::from("7")]).expect("Must run the add-on.");
+        assert!(status.success());
+        assert_eq!(fs::read_to_string(&output).expect("Must read the output."),
+            format!("lately 7 {} 1 \\\\033[1;33m {} ", settings.config.todo_file.display(), settings.config.actions_dir.with_file_name("todo.cfg").display()));
+    }
+}
@@ -0,0 +1,780 @@
+use chrono::prelude::*;
+use std::collections::BTreeSet;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Error;
+use std::io;
+use std::io::BufRead;
+use std::io::Write;
+use rustodo::Config;
+use rustodo::filter_stream;
+use rustodo::format_stream;
+use rustodo::sort_stream;
+use rustodo::Format;
+use rustodo::Query;
+use rustodo::Linter;
+use rustodo::Renderer;
+use rustodo::Severity;
+use rustodo::SortSpec;
+use rustodo::Task;
+use rustodo::TaskList;
+use rustodo::TaskRecord;
+use serde::Serialize;
+use serde_json;
+use rustodo::Theme;
+
+pub const USAGE : &str = "Usage: rustodo [-fhptTaAc] [-d CONFIG] action [task_number] [task_description]\nTry 'rustodo -h' for more information.";
+
+pub const HELP : &str = "Usage: rustodo [-fhptTaAc] [-d CONFIG] action [task_number] [task_description]
+
+Options:
+  -d CONFIG  Use a todo.sh config or a TOML config ending in .toml
+  -f         Don't ask before deleting tasks
+  -t         Prepend the current date to added tasks
+  -T         Don't prepend the current date to added tasks
+  -a         Don't archive tasks after marking them as done
+  -A         Archive tasks after marking them as done
+  -h         Show this help
+  -p         Plain mode, don't color lists
+  -c         Color lists even if the output isn't a terminal
+
+Actions:
+  add|a \"THING I NEED TO DO +project @context\"
+  append|app ITEM# \"TEXT TO APPEND\"
+  archive
+  command ACTION [ARGUMENTS...]
+  completion bash|zsh|fish
+  del|rm ITEM# [TERM]
+  depri|dp ITEM#[, ITEM#, ...]
+  do ITEM#[, ITEM#, ...]
+  filter QUERY
+  fmt [--check] [--sort[=KEYS]] [-]
+  lint [--fix]
+  list|ls [--json|--porcelain] [TERM...]
+  listcon|lsc [--json|--porcelain]
+  listproj|lsprj [--json|--porcelain]
+  prepend|prep ITEM# \"TEXT TO PREPEND\"
+  pri|p ITEM# PRIORITY
+  replace ITEM# \"UPDATED TODO\"
+  sort [KEYS]
+  tui
+
+Listings with --json print an array of objects with the fields line, completed,
+priority, completed_at, created_at, projects, contexts, options and text. With
+--porcelain they print one task per line with these fields separated by tabs,
+lists and options separated by spaces, options as key:value and the text last.
+Tags are printed as a JSON array of names, the porcelain output of tags is the
+plain one.
+
+filter, sort and fmt work as filters in pipelines: they read tasks from standard
+input and write them to standard output, e.g. rustodo filter 'due<=fri' | rustodo
+sort pri,due | rustodo fmt. fmt does so if its input is piped or with -, otherwise
+it formats the todo file. An empty pipe, like in most CI jobs, formats the todo
+file too. Lines that aren't tasks are passed on unchanged.";
+
+/// The config and the options of the command line, which override the config.
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub config: Config,
+    pub force: bool,
+    pub auto_archive: bool,
+    pub renderer: Renderer,
+    /// Whether standard input is a pipe or a redirected file, then `fmt` formats it
+    /// instead of the todo file.
+    pub piped_input: bool,
+}
+
+impl Settings {
+    /// Lists are colored with the theme of the config, unless `TODOTXT_PLAIN` is set
+    /// or the output isn't a terminal.
+    pub fn new(config : Config) -> Settings {
+        let mut renderer = Renderer::for_stdout(Theme::from_variables(&config.variables));
+        renderer.colored &= !config.flag("TODOTXT_PLAIN", false);
+        Settings {
+            force: config.flag("TODOTXT_FORCE", false),
+            auto_archive: config.flag("TODOTXT_AUTO_ARCHIVE", true),
+            renderer,
+            config,
+            piped_input: false,
+        }
+    }
+}
+
+/// A failed command, the message is printed as it is.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandError(pub String);
+
+impl Display for CommandError {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
+        write!(formatter, "{}", self.0)
+    }
+}
+
+impl From<io::Error> for CommandError {
+    fn from(error : io::Error) -> CommandError {
+        CommandError(format!("TODO: {}", error))
+    }
+}
+
+fn usage(message : &str) -> CommandError {
+    CommandError(format!("usage: rustodo {}", message))
+}
+
+type CommandResult = Result<(), CommandError>;
+
+/// Runs an action with its arguments. Messages use the wording of todo.sh, so scripts
+/// parsing its output keep working. `input` is only read to confirm deletions.
+pub fn run(settings : &Settings, action : &str, arguments : &[String], today : Date<Utc>, input : &mut dyn BufRead, output : &mut dyn Write) -> CommandResult {
+    match action {
+        "add" | "a" => add(settings, arguments, today, output),
+        "list" | "ls" => list(settings, arguments, today, output),
+        "do" => complete(settings, arguments, today, output),
+        "pri" | "p" => prioritize(settings, arguments, output),
+        "depri" | "dp" => deprioritize(settings, arguments, output),
+        "del" | "rm" => delete(settings, arguments, input, output),
+        "append" | "app" => append(settings, arguments, output),
+        "prepend" | "prep" => prepend(settings, arguments, output),
+        "replace" => replace(settings, arguments, output),
+        "archive" => archive(settings, output),
+        "listproj" | "lsprj" => list_tags(settings, arguments, output, "+", Task::projects),
+        "listcon" | "lsc" => list_tags(settings, arguments, output, "@", Task::contexts),
+        "filter" => filter(arguments, today, input, output),
+        "sort" => sort(arguments, input, output),
+        "fmt" => format(settings, arguments, today, input, output),
+        "lint" => lint(settings, arguments, output),
+        "completion" => ::completion::script(arguments, output),
+        "complete" => ::completion::complete(settings, arguments, output),
+        #[cfg(feature = "tui")]
+        "tui" => Ok(::tui::run(settings, today)?),
+        _ => Err(CommandError(format!("Unknown action '{}'.\n{}", action, USAGE))),
+    }
+}
+
+/// How listings are printed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum OutputFormat {
+    Text,
+    Json,
+    Porcelain,
+}
+
+/// Takes `--json` and `--porcelain` out of the arguments.
+fn output_format(arguments : &[String]) -> (OutputFormat, Vec<String>) {
+    let mut format = OutputFormat::Text;
+    let mut rest = Vec::new();
+    for argument in arguments {
+        match argument.as_str() {
+            "--json" => format = OutputFormat::Json,
+            "--porcelain" => format = OutputFormat::Porcelain,
+            _ => rest.push(argument.clone()),
+        }
+    }
+    (format, rest)
+}
+
+fn write_json<T: Serialize>(output : &mut dyn Write, value : &T) -> CommandResult {
+    let json = serde_json::to_string_pretty(value).map_err(|error| CommandError(error.to_string()))?;
+    writeln!(output, "{}", json)?;
+    Ok(())
+}
+
+fn task_number(argument : Option<&String>, usage_message : &str) -> Result<usize, CommandError> {
+    argument.and_then(|argument| argument.parse::<usize>().ok())
+        .ok_or_else(|| usage(usage_message))
+}
+
+/// Task numbers separated by whitespace or commas, like `1 2` or `1,2`.
+fn task_numbers(arguments : &[String], usage_message : &str) -> Result<Vec<usize>, CommandError> {
+    let numbers = arguments.iter()
+        .flat_map(|argument| argument.split(|character : char| character == ',' || character.is_whitespace()))
+        .filter(|number| !number.is_empty())
+        .map(|number| number.parse::<usize>().map_err(|_| usage(usage_message)))
+        .collect::<Result<Vec<usize>, CommandError>>()?;
+    if numbers.is_empty() {
+        return Err(usage(usage_message));
+    }
+    Ok(numbers)
+}
+
+fn task(list : &TaskList, line : usize) -> Result<Task, CommandError> {
+    list.get(line).cloned().ok_or_else(|| CommandError(format!("TODO: No task {}.", line)))
+}
+
+fn add(settings : &Settings, arguments : &[String], today : Date<Utc>, output : &mut dyn Write) -> CommandResult {
+    let text = arguments.join(" ");
+    if text.trim().is_empty() {
+        return Err(usage("add \"TODO ITEM\""));
+    }
+    let mut list = settings.config.todo_list()?;
+    let mut task = Task::new(text.trim());
+    if settings.config.date_on_add && task.created_at().is_none() {
+        task.set_created_at(Some(today));
+    }
+    let line = list.add(task);
+    list.save()?;
+    writeln!(output, "{} {}", line, task_text(&list, line))?;
+    writeln!(output, "TODO: {} added.", line)?;
+    Ok(())
+}
+
+/// Lists tasks like `todo.sh ls`: sorted by their text ignoring case, numbered with zero
+/// padded line numbers. Every term has to be part of a listed task, terms starting
+/// with `-` must not be part of it.
+fn list(settings : &Settings, arguments : &[String], today : Date<Utc>, output : &mut dyn Write) -> CommandResult {
+    let (format, terms) = output_format(arguments);
+    let list = settings.config.todo_list()?;
+    let terms = terms.iter().map(|term| term.to_lowercase()).collect::<Vec<String>>();
+    let mut tasks = list.tasks()
+        .filter(|&(_, task)| {
+            let text = task.to_string().to_lowercase();
+            terms.iter().all(|term| match term.strip_prefix('-') {
+                Some(excluded) if !excluded.is_empty() => !text.contains(excluded),
+                _ => text.contains(term.as_str()),
+            })
+        })
+        .collect::<Vec<(usize, &Task)>>();
+    let spec = SortSpec::todo_sh();
+    tasks.sort_by(|left, right| spec.compare(left.1, right.1));
+
+    match format {
+        OutputFormat::Json => {
+            let records = tasks.iter().map(|&(line, task)| TaskRecord::new(line, task)).collect::<Vec<TaskRecord>>();
+            return write_json(output, &records);
+        },
+        OutputFormat::Porcelain => {
+            for &(line, task) in &tasks {
+                writeln!(output, "{}", TaskRecord::new(line, task).porcelain())?;
+            }
+            return Ok(());
+        },
+        OutputFormat::Text => {},
+    }
+    let width = list.len().to_string().len();
+    for &(line, task) in &tasks {
+        writeln!(output, "{}", settings.renderer.render_numbered(line, width, task, today))?;
+    }
+    writeln!(output, "--")?;
+    writeln!(output, "TODO: {} of {} tasks shown", tasks.len(), list.tasks().count())?;
+    Ok(())
+}
+
+fn complete(settings : &Settings, arguments : &[String], today : Date<Utc>, output : &mut dyn Write) -> CommandResult {
+    let lines = task_numbers(arguments, "do ITEM#[, ITEM#, ...]")?;
+    let mut list = settings.config.todo_list()?;
+    //nothing is changed or printed unless all tasks exist
+    for &line in &lines {
+        task(&list, line)?;
+    }
+    for line in lines {
+        if task(&list, line)?.completed {
+            writeln!(output, "TODO: {} is already marked done.", line)?;
+            continue;
+        }
+        list.complete(line, today);
+        writeln!(output, "{} {}", line, task_text(&list, line))?;
+        writeln!(output, "TODO: {} marked as done.", line)?;
+    }
+    list.save()?;
+    if settings.auto_archive {
+        archive(settings, output)?;
+    }
+    Ok(())
+}
+
+fn prioritize(settings : &Settings, arguments : &[String], output : &mut dyn Write) -> CommandResult {
+    let usage_message = "pri ITEM# PRIORITY\nnote: PRIORITY must be anywhere from A to Z.";
+    let line = task_number(arguments.first(), usage_message)?;
+    let priority = match arguments.get(1).map(|priority| priority.to_uppercase()) {
+        Some(ref priority) if priority.len() == 1 && priority.chars().all(|character| character.is_ascii_uppercase()) =>
+            priority.chars().next(),
+        _ => return Err(usage(usage_message)),
+    };
+    let mut list = settings.config.todo_list()?;
+    task(&list, line)?;
+    list.set_priority(line, priority);
+    list.save()?;
+    writeln!(output, "{} {}", line, task_text(&list, line))?;
+    writeln!(output, "TODO: {} prioritized ({}).", line, priority.unwrap_or_default())?;
+    Ok(())
+}
+
+fn deprioritize(settings : &Settings, arguments : &[String], output : &mut dyn Write) -> CommandResult {
+    let lines = task_numbers(arguments, "depri ITEM#[, ITEM#, ...]")?;
+    let mut list = settings.config.todo_list()?;
+    for line in lines {
+        if task(&list, line)?.priority.is_none() {
+            writeln!(output, "TODO: {} is not prioritized.", line)?;
+            continue;
+        }
+        list.set_priority(line, None);
+        writeln!(output, "{} {}", line, task_text(&list, line))?;
+        writeln!(output, "TODO: {} deprioritized.", line)?;
+    }
+    list.save()?;
+    Ok(())
+}
+
+/// Deletes a task, or with a term only removes the term from the task. Deleted tasks
+/// leave a blank line behind if line numbers are preserved.
+fn delete(settings : &Settings, arguments : &[String], input : &mut dyn BufRead, output : &mut dyn Write) -> CommandResult {
+    let line = task_number(arguments.first(), "del ITEM# [TERM]")?;
+    let mut list = settings.config.todo_list()?;
+    let mut task = task(&list, line)?;
+
+    if arguments.len() > 1 {
+        let term = arguments[1..].join(" ");
+        let description = task.description().replace(&term, "");
+        let description = description.split_whitespace().collect::<Vec<&str>>().join(" ");
+        writeln!(output, "{} {}", line, task)?;
+        if description == task.description() {
+            writeln!(output, "TODO: '{}' not found; no removal done.", term)?;
+            return Ok(());
+        }
+        task.set_description(&description);
+        list.replace(line, task);
+        list.save()?;
+        writeln!(output, "TODO: Removed '{}' from task.", term)?;
+        writeln!(output, "{} {}", line, task_text(&list, line))?;
+        return Ok(());
+    }
+
+    if !settings.force {
+        writeln!(output, "Delete '{}'?  (y/n)", task)?;
+        output.flush()?;
+        let mut answer = String::new();
+        input.read_line(&mut answer)?;
+        if answer.trim() != "y" {
+            writeln!(output, "TODO: No tasks were deleted.")?;
+            return Ok(());
+        }
+    }
+    if settings.config.preserve_line_numbers {
+        list.clear(line);
+    } else {
+        list.remove(line);
+    }
+    list.save()?;
+    writeln!(output, "{} {}", line, task)?;
+    writeln!(output, "TODO: {} deleted.", line)?;
+    Ok(())
+}
+
+fn append(settings : &Settings, arguments : &[String], output : &mut dyn Write) -> CommandResult {
+    edit_description(settings, arguments, "append ITEM# \"TEXT TO APPEND\"", output, |description, text| {
+        //like todo.sh, punctuation is appended without a space
+        if text.starts_with(|character : char| ",.:;".contains(character)) {
+            format!("{}{}", description, text)
+        } else {
+            format!("{} {}", description, text)
+        }
+    })
+}
+
+fn prepend(settings : &Settings, arguments : &[String], output : &mut dyn Write) -> CommandResult {
+    edit_description(settings, arguments, "prepend ITEM# \"TEXT TO PREPEND\"", output, |description, text| format!("{} {}", text, description))
+}
+
+fn edit_description<F>(settings : &Settings, arguments : &[String], usage_message : &str, output : &mut dyn Write, edit : F) -> CommandResult
+    where F: Fn(&str, &str) -> String {
+    let line = task_number(arguments.first(), usage_message)?;
+    let text = arguments[1..].join(" ");
+    if text.trim().is_empty() {
+        return Err(usage(usage_message));
+    }
+    let mut list = settings.config.todo_list()?;
+    let mut task = task(&list, line)?;
+    task.set_description(edit(&task.description(), text.trim()).trim());
+    list.replace(line, task);
+    list.save()?;
+    writeln!(output, "{} {}", line, task_text(&list, line))?;
+    Ok(())
+}
+
+/// Replaces a task, keeping its priority and creation date unless the new text has its own.
+fn replace(settings : &Settings, arguments : &[String], output : &mut dyn Write) -> CommandResult {
+    let usage_message = "replace ITEM# \"UPDATED ITEM\"";
+    let line = task_number(arguments.first(), usage_message)?;
+    let text = arguments[1..].join(" ");
+    if text.trim().is_empty() {
+        return Err(usage(usage_message));
+    }
+    let mut list = settings.config.todo_list()?;
+    let old = task(&list, line)?;
+    let mut new = Task::new(text.trim());
+    if new.priority.is_none() {
+        new.priority = old.priority;
+    }
+    if new.created_at().is_none() {
+        new.set_created_at(old.created_at());
+    }
+    list.replace(line, new);
+    list.save()?;
+    writeln!(output, "{} {}", line, old)?;
+    writeln!(output, "TODO: Replaced task with:")?;
+    writeln!(output, "{} {}", line, task_text(&list, line))?;
+    Ok(())
+}
+
+/// Moves completed tasks to the done file. The done file is written first, so a
+/// failure never loses tasks.
+fn archive(settings : &Settings, output : &mut dyn Write) -> CommandResult {
+    let mut list = settings.config.todo_list()?;
+    let mut done = settings.config.done_list()?;
+    let completed_lines = list.tasks()
+        .filter(|&(_, task)| task.completed)
+        .map(|(line, _)| line)
+        .collect::<Vec<usize>>();
+
+    let mut archived = Vec::new();
+    for &line in completed_lines.iter().rev() {
+        archived.extend(list.remove(line));
+    }
+    archived.reverse();
+    for task in &archived {
+        done.add(task.clone());
+    }
+    if !settings.config.preserve_line_numbers {
+        list.remove_blank_lines();
+    }
+
+    done.save()?;
+    list.save()?;
+    for task in &archived {
+        writeln!(output, "{}", task)?;
+    }
+    writeln!(output, "TODO: {} archived.", settings.config.todo_file.display())?;
+    Ok(())
+}
+
+/// Passes on the tasks of the input matching a query, see `filter_stream`.
+fn filter(arguments : &[String], today : Date<Utc>, input : &mut dyn BufRead, output : &mut dyn Write) -> CommandResult {
+    let text = arguments.join(" ");
+    if text.trim().is_empty() {
+        return Err(usage("filter QUERY"));
+    }
+    let query = Query::parse(&text).map_err(|error| CommandError(error.highlight(&text)))?;
+    filter_stream(input, output, &query, today)?;
+    Ok(())
+}
+
+/// Sorts the tasks of the input by keys like `pri,due`, see `sort_stream`.
+fn sort(arguments : &[String], input : &mut dyn BufRead, output : &mut dyn Write) -> CommandResult {
+    let spec = match arguments.first() {
+        Some(keys) => SortSpec::parse(keys).map_err(|error| CommandError(error.to_string()))?,
+        None => SortSpec::default(),
+    };
+    sort_stream(input, output, &spec)?;
+    Ok(())
+}
+
+/// Rewrites the todo file in canonical form, see `Format`. The format comes from the
+/// `format` table of a TOML config, `--sort` sorts by its keys or the default ones.
+/// With `--check` the file stays as it is and the command fails if it isn't formatted.
+/// With piped input or `-` the input is formatted to the output instead.
+fn format(settings : &Settings, arguments : &[String], today : Date<Utc>, input : &mut dyn BufRead, output : &mut dyn Write) -> CommandResult {
+    let usage_message = "fmt [--check] [--sort[=KEYS]] [-]";
+    let mut format = match settings.config.toml_path() {
+        Some(path) => Format::load_config(path).map_err(|error| CommandError(error.to_string()))?,
+        None => Format::default(),
+    };
+    let mut check = false;
+    let mut stream = false;
+    for argument in arguments {
+        match argument.as_str() {
+            "--check" => check = true,
+            "-" => stream = true,
+            "--sort" => format.sort = Some(format.sort.unwrap_or_default()),
+            argument => match argument.strip_prefix("--sort=") {
+                Some(keys) => format.sort = Some(SortSpec::parse(keys).map_err(|error| CommandError(error.to_string()))?),
+                None => return Err(usage(usage_message)),
+            },
+        }
+    }
+
+    //the input is read first, an empty pipe like in CI jobs still checks the todo file
+    let mut piped = Vec::new();
+    if !stream && settings.piped_input {
+        input.read_to_end(&mut piped)?;
+        stream = !piped.is_empty();
+    }
+    if stream {
+        let mut buffered = &piped[..];
+        let input : &mut dyn BufRead = if piped.is_empty() { input } else { &mut buffered };
+        let changed = if check {
+            format_stream(input, &mut io::sink(), &format, today)?
+        } else {
+            format_stream(input, output, &format, today)?
+        };
+        if check && changed > 0 {
+            return Err(CommandError(format!("TODO: The input isn't formatted, {} lines would change.", changed)));
+        }
+        return Ok(());
+    }
+
+    let mut list = settings.config.todo_list()?;
+    let diff = format.format(&mut list, today, check);
+    let name = settings.config.todo_file.display();
+    if check {
+        write!(output, "{}", diff)?;
+        if !diff.is_empty() {
+            return Err(CommandError(format!("TODO: {} isn't formatted, {} lines would change.", name, diff.len())));
+        }
+        writeln!(output, "TODO: {} is formatted.", name)?;
+        return Ok(());
+    }
+    list.save()?;
+    writeln!(output, "TODO: {} lines of {} formatted.", diff.len(), name)?;
+    Ok(())
+}
+
+/// Reports the problems of the todo file, with `--fix` after fixing what can be fixed.
+/// The rules come from the `lint` table of a TOML config. Fails if errors remain.
+fn lint(settings : &Settings, arguments : &[String], output : &mut dyn Write) -> CommandResult {
+    let fix = match arguments.first().map(String::as_str) {
+        Some("--fix") => true,
+        None => false,
+        _ => return Err(usage("lint [--fix]")),
+    };
+    let linter = match settings.config.toml_path() {
+        Some(path) => Linter::load_config(path).map_err(|error| CommandError(error.to_string()))?,
+        None => Linter::default(),
+    };
+    let mut list = settings.config.todo_list()?;
+    if fix {
+        let fixed = linter.fix(&mut list);
+        list.save()?;
+        for problem in &fixed {
+            writeln!(output, "{} (fixed)", problem)?;
+        }
+        writeln!(output, "TODO: {} problems fixed.", fixed.len())?;
+    }
+
+    let problems = linter.lint(&list);
+    for problem in &problems {
+        writeln!(output, "{}", problem)?;
+    }
+    writeln!(output, "TODO: {} problems found.", problems.len())?;
+    match problems.iter().filter(|problem| problem.severity == Severity::Error).count() {
+        0 => Ok(()),
+        errors => Err(CommandError(format!("TODO: {} of the problems are errors.", errors))),
+    }
+}
+
+fn list_tags(settings : &Settings, arguments : &[String], output : &mut dyn Write, sigil : &str, tags : fn(&Task) -> Vec<String>) -> CommandResult {
+    let (format, _) = output_format(arguments);
+    let list = settings.config.todo_list()?;
+    let tags = list.tasks().flat_map(|(_, task)| tags(task)).collect::<BTreeSet<String>>();
+    if format == OutputFormat::Json {
+        return write_json(output, &tags);
+    }
+    for tag in tags {
+        writeln!(output, "{}{}", sigil, tag)?;
+    }
+    Ok(())
+}
+
+fn task_text(list : &TaskList, line : usize) -> String {
+    list.get(line).map(|task| task.to_string()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use commands::*;
+    use rustodo::Variables;
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    fn settings(name : &str, todo : &str) -> Settings {
+        let directory = env::temp_dir().join(format!("rustodo-cli-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).expect("Must create test directory.");
+        fs::write(directory.join("todo.txt"), todo).expect("Must write todo.txt.");
+        let mut config = Config::parse_shell("", None, &Variables::new());
+        config.todo_file = directory.join("todo.txt");
+        config.done_file = directory.join("done.txt");
+        Settings {
+            config,
+            force: true,
+            auto_archive: false,
+            renderer: Renderer::new(Theme::default(), false),
+            piped_input: false,
+        }
+    }
+
+    fn run_with_input(settings : &Settings, command : &str, input : &str) -> Result<String, CommandError> {
+        let words = command.split(' ').map(String::from).collect::<Vec<String>>();
+        let mut output = Vec::new();
+        run(settings, &words[0], &words[1..], Utc.ymd(2018, 11, 24), &mut input.as_bytes(), &mut output)?;
+        Ok(String::from_utf8(output).expect("Output must be UTF-8."))
+    }
+
+    fn run_command(settings : &Settings, command : &str) -> String {
+        run_with_input(settings, command, "").expect("Command must succeed.")
+    }
+
+    fn todo(settings : &Settings) -> String {
+        fs::read_to_string(&settings.config.todo_file).expect("Must read todo.txt.")
+    }
+
+    #[test]
+    fn ls_sorts_and_pads_like_todo_sh() {
+        let settings = settings("ls", "call mom\n(B) Beta +work\n\nBuy milk @market\n(A) Alpha\n5\n6\n7\n8\n9\n");
+
+        assert_eq!(run_command(&settings, "ls"),
+            "05 (A) Alpha\n02 (B) Beta +work\n06 5\n07 6\n08 7\n09 8\n10 9\n04 Buy milk @market\n01 call mom\n--\nTODO: 9 of 9 tasks shown\n");
+        assert_eq!(run_command(&settings, "list b -milk"), "02 (B) Beta +work\n--\nTODO: 1 of 9 tasks shown\n");
+
+        let mut settings = settings;
+        settings.renderer.colored = true;
+        assert_eq!(run_command(&settings, "ls milk"), "04 Buy milk \x1b[0;36m@market\x1b[0m\n--\nTODO: 1 of 9 tasks shown\n");
+    }
+
+    #[test]
+    fn lint_reports_and_fixes_problems() {
+        let settings = settings("lint", "x 2018-11-24 Pay rent\nCall mom\nCall mom\n");
+
+        let mut output = Vec::new();
+        let error = run(&settings, "lint", &[], Utc.ymd(2018, 11, 24), &mut "".as_bytes(), &mut output).expect_err("Must fail.");
+        assert_eq!(error.to_string(), "TODO: 1 of the problems are errors.");
+        assert_eq!(String::from_utf8(output).expect("Output must be UTF-8."),
+            "1: error: Completion date without a creation date. [completion-without-creation]\n3: warning: Same task as on line 2. [duplicate-task]\nTODO: 2 problems found.\n");
+
+        assert!(run_command(&settings, "lint --fix").ends_with("TODO: 2 problems fixed.\nTODO: 0 problems found.\n"));
+        assert_eq!(todo(&settings), "x 2018-11-24 2018-11-24 Pay rent\nCall mom\n");
+    }
+
+    #[test]
+    fn fmt_checks_and_rewrites_the_file() {
+        let settings = settings("fmt", "Write  docs due:2018-11-30\n(A) Fix bug\n");
+
+        let mut output = Vec::new();
+        let error = run(&settings, "fmt", &[String::from("--check")], Utc.ymd(2018, 11, 24), &mut "".as_bytes(), &mut output).expect_err("Must fail.");
+        assert_eq!(String::from_utf8(output).expect("Output must be UTF-8."), "-1 Write  docs due:2018-11-30\n+1 Write docs due:2018-11-30\n");
+        assert!(error.to_string().ends_with("isn't formatted, 1 lines would change."));
+
+        assert!(run_command(&settings, "fmt --sort").starts_with("TODO: 2 lines of"));
+        assert_eq!(todo(&settings), "(A) Fix bug\nWrite docs due:2018-11-30\n");
+        assert!(run_command(&settings, "fmt --check --sort=pri").ends_with("is formatted.\n"));
+        assert!(run_with_input(&settings, "fmt --sort=size", "").is_err());
+    }
+
+    #[test]
+    fn listings_can_be_json_or_porcelain() {
+        let settings = settings("json", "Write docs +rustodo\n(A) 2018-11-20 Call mom @phone due:2018-11-30\n");
+
+        let json : serde_json::Value = serde_json::from_str(&run_command(&settings, "ls --json mom")).expect("Must be JSON.");
+        assert_eq!(json, serde_json::json!([{
+            "line": 2,
+            "completed": false,
+            "priority": "A",
+            "completed_at": null,
+            "created_at": "2018-11-20",
+            "projects": [],
+            "contexts": ["phone"],
+            "options": { "due": "2018-11-30" },
+            "text": "(A) 2018-11-20 Call mom @phone due:2018-11-30",
+        }]));
+        assert_eq!(run_command(&settings, "ls --porcelain"),
+            "2\t\tA\t\t2018-11-20\t\tphone\tdue:2018-11-30\t(A) 2018-11-20 Call mom @phone due:2018-11-30\n1\t\t\t\t\trustodo\t\t\tWrite docs +rustodo\n");
+        assert_eq!(run_command(&settings, "lsprj --json"), "[\n  \"rustodo\"\n]\n");
+        assert_eq!(run_command(&settings, "lsc --porcelain"), "@phone\n");
+    }
+
+    #[test]
+    fn filter_sort_and_fmt_work_on_streams() {
+        let mut settings = settings("stream", "Untouched\n");
+        let input = "# Sprint\nWrite  docs due:2018-11-30\n(A) Fix bug +rustodo\nCall mom\n(B) Plan +rustodo due:2018-11-26\n";
+
+        let filtered = run_with_input(&settings, "filter not due>=2018-11-27", input).expect("Must filter.");
+        assert_eq!(filtered, "# Sprint\n(A) Fix bug +rustodo\nCall mom\n(B) Plan +rustodo due:2018-11-26\n");
+        let sorted = run_with_input(&settings, "sort pri", &filtered).expect("Must sort.");
+        assert_eq!(sorted, "# Sprint\n(A) Fix bug +rustodo\n(B) Plan +rustodo due:2018-11-26\nCall mom\n");
+        assert_eq!(run_with_input(&settings, "fmt -", input).expect("Must format."),
+            "# Sprint\nWrite docs due:2018-11-30\n(A) Fix bug +rustodo\nCall mom\n(B) Plan +rustodo due:2018-11-26\n");
+        assert!(run_with_input(&settings, "fmt --check -", input).is_err());
+        assert_eq!(todo(&settings), "Untouched\n");
+
+        //rustodo filter 'not due>=2018-11-27' | rustodo sort pri,due | rustodo fmt
+        settings.piped_input = true;
+        let filtered = run_with_input(&settings, "filter not due>=2018-11-27", input).expect("Must filter.");
+        let sorted = run_with_input(&settings, "sort pri,due", &filtered).expect("Must sort.");
+        assert_eq!(run_with_input(&settings, "fmt", &sorted).expect("Must format."),
+            "# Sprint\n(A) Fix bug +rustodo\n(B) Plan +rustodo due:2018-11-26\nCall mom\n");
+        assert_eq!(todo(&settings), "Untouched\n");
+
+        //an empty pipe checks and formats the todo file
+        let mut settings = self::settings("stream-file", "Pay  rent\n");
+        settings.piped_input = true;
+        assert!(run_with_input(&settings, "fmt --check", "").is_err());
+        run_with_input(&settings, "fmt", "").expect("Must format the file.");
+        assert_eq!(todo(&settings), "Pay rent\n");
+
+        let error = run_with_input(&settings, "filter due<=", input).expect_err("Must fail.");
+        assert!(error.to_string().starts_with("due<=\n"), "{}", error);
+    }
+
+    #[test]
+    fn add_appends_a_numbered_task() {
+        let mut settings = settings("add", "Call mom\n");
+        settings.config.date_on_add = true;
+
+        assert_eq!(run_command(&settings, "add (A) Pay rent due:tomorrow"),
+            format!("2 (A) 2018-11-24 Pay rent due:{}\nTODO: 2 added.\n", Utc::today().succ().format("%F")));
+        assert!(run_with_input(&settings, "add", "").is_err());
+    }
+
+    #[test]
+    fn do_pri_and_depri_edit_tasks() {
+        let mut settings = settings("do", "2018-11-20 Call mom\nWrite report\n");
+
+        assert_eq!(run_command(&settings, "pri 2 b"), "2 (B) Write report\nTODO: 2 prioritized (B).\n");
+        assert_eq!(run_command(&settings, "depri 2"), "2 Write report\nTODO: 2 deprioritized.\n");
+        assert_eq!(run_command(&settings, "depri 2"), "TODO: 2 is not prioritized.\n");
+        assert_eq!(run_with_input(&settings, "pri 3 A", ""), Err(CommandError(String::from("TODO: No task 3."))));
+
+        settings.auto_archive = true;
+        assert_eq!(run_command(&settings, "do 1"), format!(
+            "1 x 2018-11-24 2018-11-20 Call mom\nTODO: 1 marked as done.\nx 2018-11-24 2018-11-20 Call mom\nTODO: {} archived.\n",
+            settings.config.todo_file.display()));
+        assert_eq!(todo(&settings), "Write report\n");
+        assert_eq!(fs::read_to_string(&settings.config.done_file).expect("Must read done.txt."), "x 2018-11-24 2018-11-20 Call mom\n");
+
+        settings.auto_archive = false;
+        assert_eq!(run_with_input(&settings, "do 1,99", ""), Err(CommandError(String::from("TODO: No task 99."))));
+        assert_eq!(todo(&settings), "Write report\n");
+        assert_eq!(run_command(&settings, "do 1"), "1 x 2018-11-24 2018-11-24 Write report\nTODO: 1 marked as done.\n");
+    }
+
+    #[test]
+    fn del_keeps_line_numbers_and_asks_first() {
+        let mut settings = settings("del", "Call mom\nWrite report +work\n");
+
+        settings.force = false;
+        assert_eq!(run_with_input(&settings, "del 1", "n\n").expect("Must succeed."), "Delete 'Call mom'?  (y/n)\nTODO: No tasks were deleted.\n");
+        assert_eq!(run_with_input(&settings, "del 1", "y\n").expect("Must succeed."), "Delete 'Call mom'?  (y/n)\n1 Call mom\nTODO: 1 deleted.\n");
+        assert_eq!(todo(&settings), "\nWrite report +work\n");
+
+        assert_eq!(run_command(&settings, "del 2 +work"), "2 Write report +work\nTODO: Removed '+work' from task.\n2 Write report\n");
+        assert_eq!(run_command(&settings, "del 2 +home"), "2 Write report\nTODO: '+home' not found; no removal done.\n");
+    }
+
+    #[test]
+    fn append_prepend_and_replace_change_the_text() {
+        let settings = settings("append", "(A) 2018-11-20 Call mom\n");
+
+        assert_eq!(run_command(&settings, "append 1 about dinner"), "1 (A) 2018-11-20 Call mom about dinner\n");
+        assert_eq!(run_command(&settings, "append 1 , quickly"), "1 (A) 2018-11-20 Call mom about dinner, quickly\n");
+        assert_eq!(run_command(&settings, "prepend 1 Please"), "1 (A) 2018-11-20 Please Call mom about dinner, quickly\n");
+        assert_eq!(run_command(&settings, "replace 1 Call dad"),
+            "1 (A) 2018-11-20 Please Call mom about dinner, quickly\nTODO: Replaced task with:\n1 (A) 2018-11-20 Call dad\n");
+    }
+
+    #[test]
+    fn listproj_and_listcon_list_unique_tags() {
+        let settings = settings("tags", "Fix bug +rustodo @office\nCall mom @phone\nWrite docs +rustodo +docs\n");
+
+        assert_eq!(run_command(&settings, "listproj"), "+docs\n+rustodo\n");
+        assert_eq!(run_command(&settings, "listcon"), "@office\n@phone\n");
+    }
+}
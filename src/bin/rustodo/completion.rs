@@ -0,0 +1,160 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::io::Write;
+use rustodo::Config;
+use rustodo::Task;
+use addons;
+use commands::CommandError;
+use commands::Settings;
+
+/// The actions offered when the action itself is completed.
+const ACTIONS : [&str; 19] = [
+    "add", "append", "archive", "command", "completion", "del", "depri", "do", "filter", "fmt", "lint",
+    "list", "listcon", "listproj", "prepend", "pri", "replace", "sort", "tui",
+];
+
+const BASH : &str = r#"# rustodo completion for bash, load it with: source <(rustodo completion bash)
+_rustodo() {
+    local IFS=$'\n'
+    COMPREPLY=($(rustodo complete "${COMP_WORDS[@]:1:COMP_CWORD}" 2>/dev/null))
+    if [[ ${#COMPREPLY[@]} -eq 1 && ${COMPREPLY[0]} == *: ]]; then
+        compopt -o nospace
+    fi
+}
+complete -F _rustodo rustodo
+"#;
+
+const ZSH : &str = r#"#compdef rustodo
+# rustodo completion for zsh, load it with: source <(rustodo completion zsh)
+_rustodo() {
+    local -a candidates
+    candidates=(${(f)"$(rustodo complete "${(@)words[2,CURRENT]}" 2>/dev/null)"})
+    compadd -Q -S '' -- ${(M)candidates:#*:}
+    compadd -Q -- ${candidates:#*:}
+}
+compdef _rustodo rustodo
+"#;
+
+const FISH : &str = r#"# rustodo completion for fish, load it with: rustodo completion fish | source
+complete -c rustodo -f -a '(rustodo complete (commandline -opc)[2..-1] (commandline -ct))'
+"#;
+
+/// Prints the completion script for a shell.
+pub fn script(arguments : &[String], output : &mut dyn Write) -> Result<(), CommandError> {
+    let script = match arguments.first().map(String::as_str) {
+        Some("bash") => BASH,
+        Some("zsh") => ZSH,
+        Some("fish") => FISH,
+        _ => return Err(CommandError(String::from("usage: rustodo completion bash|zsh|fish"))),
+    };
+    write!(output, "{}", script)?;
+    Ok(())
+}
+
+/// Prints the candidates for the last word of a command line, one per line. The words
+/// are the command line after `rustodo`, options included, so `-d CONFIG` is honored.
+///
+/// The first word after the options completes to actions. Other words complete to the
+/// projects of the list if they start with `+`, to contexts if they start with `@` and
+/// to option keys like `due:` otherwise. Only lines that can contain a candidate are
+/// parsed, so completing stays fast on large files.
+pub fn complete(settings : &Settings, words : &[String], output : &mut dyn Write) -> Result<(), CommandError> {
+    let mut config = None;
+    let mut position = 0;
+    while position + 1 < words.len() && words[position].len() > 1 && words[position].starts_with('-') {
+        if words[position].ends_with('d') && position + 2 < words.len() {
+            position += 1;
+            config = Config::load(&words[position]).ok();
+        }
+        position += 1;
+    }
+    let word = match words.last() {
+        Some(word) => word.as_str(),
+        None => return Ok(()),
+    };
+
+    if position + 1 == words.len() {
+        let config = config.as_ref().unwrap_or(&settings.config);
+        let mut actions = ACTIONS.iter()
+            .filter(|&&action| action != "tui" || cfg!(feature = "tui"))
+            .map(|&action| String::from(action))
+            .chain(addons::list(config))
+            .collect::<Vec<String>>();
+        actions.sort();
+        actions.dedup();
+        for action in actions.iter().filter(|action| action.starts_with(word)) {
+            writeln!(output, "{}", action)?;
+        }
+        return Ok(());
+    }
+    if word.is_empty() {
+        return Ok(());
+    }
+
+    let config = config.as_ref().unwrap_or(&settings.config);
+    let content = match fs::read_to_string(&config.todo_file) {
+        Ok(content) => content,
+        Err(ref error) if error.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(error) => return Err(error.into()),
+    };
+    let (marker, tags) : (&str, fn(&Task) -> Vec<String>) = match word.chars().next() {
+        Some('+') => ("+", |task| task.projects().into_iter().map(|project| format!("+{}", project)).collect()),
+        Some('@') => ("@", |task| task.contexts().into_iter().map(|context| format!("@{}", context)).collect()),
+        _ if !word.contains(':') => (":", |task| task.options().into_keys().map(|key| format!("{}:", key)).collect()),
+        _ => return Ok(()),
+    };
+    let candidates = content.lines()
+        .filter(|line| line.contains(marker))
+        .flat_map(|line| tags(&Task::new(line)))
+        .filter(|candidate| candidate.starts_with(word))
+        .collect::<BTreeSet<String>>();
+    for candidate in candidates {
+        writeln!(output, "{}", candidate)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use completion::*;
+    use rustodo::Renderer;
+    use rustodo::Theme;
+    use rustodo::Variables;
+    use std::env;
+    use std::process;
+
+    fn candidates(words : &[&str]) -> String {
+        let directory = env::temp_dir().join(format!("rustodo-completion-{}", process::id()));
+        fs::create_dir_all(&directory).expect("Must create test directory.");
+        fs::write(directory.join("todo.txt"), "Write docs +rustodo @desk due:2018-12-01\nCall mom @phone\nPlan +rust t:2018-11-30\n")
+            .expect("Must write todo.txt.");
+        let mut config = Config::parse_shell("", None, &Variables::new());
+        config.todo_file = directory.join("todo.txt");
+        let settings = Settings { config, force: false, auto_archive: false, renderer: Renderer::new(Theme::default(), false), piped_input: false };
+
+        let words = words.iter().map(|&word| String::from(word)).collect::<Vec<String>>();
+        let mut output = Vec::new();
+        complete(&settings, &words, &mut output).expect("Must complete.");
+        String::from_utf8(output).expect("Output must be UTF-8.")
+    }
+
+    #[test]
+    fn completes_actions_tags_and_keys() {
+        assert_eq!(candidates(&["-p", "co"]), "command\ncompletion\n");
+        assert_eq!(candidates(&["-p", "li"]), "lint\nlist\nlistcon\nlistproj\n");
+        assert_eq!(candidates(&["add", "Plan", "+ru"]), "+rust\n+rustodo\n");
+        assert_eq!(candidates(&["add", "@"]), "@desk\n@phone\n");
+        assert_eq!(candidates(&["add", "d"]), "due:\n");
+        assert_eq!(candidates(&["add", ""]), "");
+    }
+
+    #[test]
+    fn prints_scripts_for_known_shells() {
+        let mut output = Vec::new();
+        script(&[String::from("fish")], &mut output).expect("Must print the script.");
+
+        assert!(String::from_utf8(output).expect("Output must be UTF-8.").contains("rustodo complete"));
+        assert!(script(&[String::from("tcsh")], &mut Vec::new()).is_err());
+    }
+}
@@ -0,0 +1,111 @@
+extern crate chrono;
+#[cfg(feature = "tui")]
+extern crate crossterm;
+extern crate rustodo;
+extern crate serde;
+extern crate serde_json;
+
+mod addons;
+mod commands;
+mod completion;
+#[cfg(feature = "tui")]
+mod tui;
+
+use chrono::prelude::*;
+use std::env;
+use std::fs;
+use std::io;
+use std::process;
+use rustodo::Config;
+use commands::Settings;
+
+fn main() {
+    let arguments = env::args().skip(1).collect::<Vec<String>>();
+
+    //options come before the action, like in todo.sh
+    let mut flags = Vec::new();
+    let mut config_path = None;
+    let mut position = 0;
+    while let Some(argument) = arguments.get(position).filter(|argument| argument.len() > 1 && argument.starts_with('-')) {
+        for flag in argument[1..].chars() {
+            if flag == 'd' {
+                position += 1;
+                config_path = Some(arguments.get(position).unwrap_or_else(|| fail(commands::USAGE)));
+            } else {
+                flags.push(flag);
+            }
+        }
+        position += 1;
+    }
+
+    let config = match config_path {
+        Some(path) => Config::load(path),
+        None => Config::load_default(),
+    };
+    let mut settings = Settings::new(config.unwrap_or_else(|error| fail(&error.to_string())));
+    settings.piped_input = input_is_piped();
+    for flag in flags {
+        match flag {
+            'f' => settings.force = true,
+            't' => settings.config.date_on_add = true,
+            'T' => settings.config.date_on_add = false,
+            'a' => settings.auto_archive = false,
+            'A' => settings.auto_archive = true,
+            'p' => settings.renderer.colored = false,
+            'c' => settings.renderer.colored = true,
+            'h' => {
+                println!("{}", commands::HELP);
+                let addons = addons::list(&settings.config);
+                if !addons.is_empty() {
+                    println!("\nAdd-on Actions:\n  {}", addons.join("\n  "));
+                }
+                return;
+            },
+            _ => fail(&format!("Unknown option '-{}'.\n{}", flag, commands::USAGE)),
+        }
+    }
+
+    let action = match arguments.get(position) {
+        Some(action) => action,
+        None => fail(commands::USAGE),
+    };
+
+    //add-ons take precedence over built-in actions, `command` runs the built-in one
+    let action = match arguments.get(position + 1) {
+        Some(builtin) if action == "command" => {
+            position += 1;
+            builtin
+        },
+        _ => {
+            if let Some(path) = addons::find(&settings.config, action) {
+                let status = addons::run(&settings, &path, action, &arguments[position + 1..]).unwrap_or_else(|error| fail(&error.to_string()));
+                process::exit(status.code().unwrap_or(1));
+            }
+            action
+        },
+    };
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let result = commands::run(&settings, action, &arguments[position + 1..], Utc::today(), &mut stdin.lock(), &mut stdout.lock());
+    if let Err(error) = result {
+        fail(&error.to_string());
+    }
+}
+
+//a pipe or a redirected file, but not a terminal or /dev/null
+#[cfg(unix)]
+fn input_is_piped() -> bool {
+    use std::os::unix::fs::FileTypeExt;
+    fs::metadata("/dev/stdin").is_ok_and(|metadata| metadata.file_type().is_fifo() || metadata.file_type().is_file())
+}
+
+#[cfg(not(unix))]
+fn input_is_piped() -> bool {
+    use std::io::IsTerminal;
+    !io::stdin().is_terminal()
+}
+
+fn fail(message : &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(1)
+}
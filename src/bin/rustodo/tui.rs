@@ -0,0 +1,664 @@
+use chrono::prelude::*;
+use crossterm::cursor;
+use crossterm::event;
+use crossterm::event::Event;
+use crossterm::event::KeyCode;
+use crossterm::event::KeyEventKind;
+use crossterm::event::KeyModifiers;
+use crossterm::queue;
+use crossterm::terminal;
+use std::collections::BTreeSet;
+use std::io;
+use std::io::Write;
+use std::time::Duration;
+use rustodo::Query;
+use rustodo::Renderer;
+use rustodo::SortSpec;
+use rustodo::SyncReport;
+use rustodo::Task;
+use rustodo::TaskList;
+use commands::Settings;
+
+const RESET : &str = "\x1b[0m";
+const SEPARATOR : &str = " | ";
+
+/// The keys the interface reacts to, independent of the terminal library.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Key {
+    Char(char),
+    Enter,
+    Escape,
+    Backspace,
+    Delete,
+    Tab,
+    Left,
+    Right,
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Mode {
+    Tasks,
+    Tags,
+    Filter,
+    //the line of the edited task, `None` for a new one
+    Edit(Option<usize>),
+}
+
+/// The state of the full-screen interface: a list of tasks filtered by a query and
+/// sorted like `ls`, next to a pane with the projects and contexts of the list.
+///
+/// Every change is saved right away with `TaskList::sync`, so changes other programs
+/// made to the file in the meantime aren't overwritten.
+pub struct App {
+    list: TaskList,
+    renderer: Renderer,
+    today: Date<Utc>,
+    preserve_line_numbers: bool,
+    mode: Mode,
+    filter: String,
+    query: Option<Query>,
+    //the lines of the shown tasks, in the order they are shown
+    visible: Vec<usize>,
+    selected: usize,
+    scroll: usize,
+    tags: Vec<String>,
+    selected_tag: usize,
+    input: Vec<char>,
+    cursor: usize,
+    message: String,
+    quit: bool,
+}
+
+impl App {
+    pub fn new(list : TaskList, renderer : Renderer, today : Date<Utc>, preserve_line_numbers : bool) -> App {
+        let mut app = App {
+            list,
+            renderer,
+            today,
+            preserve_line_numbers,
+            mode: Mode::Tasks,
+            filter: String::new(),
+            query: None,
+            visible: Vec::new(),
+            selected: 0,
+            scroll: 0,
+            tags: Vec::new(),
+            selected_tag: 0,
+            input: Vec::new(),
+            cursor: 0,
+            message: String::from("Press ? for help."),
+            quit: false,
+        };
+        app.update();
+        app
+    }
+
+    pub fn has_quit(&self) -> bool {
+        self.quit
+    }
+
+    /// The line of the selected task.
+    pub fn selected_line(&self) -> Option<usize> {
+        self.visible.get(self.selected).cloned()
+    }
+
+    /// Picks up changes other programs made to the file.
+    pub fn refresh(&mut self) -> io::Result<()> {
+        let report = self.list.merge_external_changes()?;
+        if report.changed_externally {
+            self.report(&report, "Reloaded the changed file.");
+        }
+        Ok(())
+    }
+
+    pub fn handle_key(&mut self, key : Key) -> io::Result<()> {
+        match self.mode {
+            Mode::Tasks => self.handle_task_key(key)?,
+            Mode::Tags => self.handle_tag_key(key),
+            Mode::Filter => self.handle_filter_key(key),
+            Mode::Edit(line) => self.handle_edit_key(key, line)?,
+        }
+        Ok(())
+    }
+
+    fn handle_task_key(&mut self, key : Key) -> io::Result<()> {
+        let line = self.selected_line();
+        match key {
+            Key::Char('q') | Key::Escape => self.quit = true,
+            Key::Char('?') => self.message = String::from(
+                "j/k move, / filter, e edit, n new, x done, A-Z priority, 0 no priority, d delete, u undo, tab tags, q quit"),
+            Key::Char('j') | Key::Down => self.select(self.selected as isize + 1),
+            Key::Char('k') | Key::Up => self.select(self.selected as isize - 1),
+            Key::PageDown => self.select(self.selected as isize + 10),
+            Key::PageUp => self.select(self.selected as isize - 10),
+            Key::Char('g') | Key::Home => self.select(0),
+            Key::End => self.select(self.visible.len() as isize - 1),
+            Key::Tab => self.mode = Mode::Tags,
+            Key::Char('/') => {
+                self.mode = Mode::Filter;
+                self.set_input(&self.filter.clone());
+            },
+            Key::Char('n') => {
+                self.mode = Mode::Edit(None);
+                self.set_input("");
+            },
+            _ => match (key, line) {
+                (Key::Char('e'), Some(line)) | (Key::Enter, Some(line)) => {
+                    self.mode = Mode::Edit(Some(line));
+                    let text = self.list.get(line).map(Task::to_string).unwrap_or_default();
+                    self.set_input(&text);
+                },
+                (Key::Char('x'), Some(line)) if !self.list.get(line).is_some_and(|task| task.completed) => {
+                    self.list.complete(line, self.today);
+                    self.save(&format!("Completed {}.", line))?;
+                },
+                (Key::Char(priority), Some(line)) if priority.is_ascii_uppercase() => {
+                    self.list.set_priority(line, Some(priority));
+                    self.save(&format!("Prioritized {} ({}).", line, priority))?;
+                },
+                (Key::Char('0'), Some(line)) | (Key::Char('-'), Some(line)) => {
+                    self.list.set_priority(line, None);
+                    self.save(&format!("Deprioritized {}.", line))?;
+                },
+                (Key::Char('d'), Some(line)) => {
+                    if self.preserve_line_numbers {
+                        self.list.clear(line);
+                    } else {
+                        self.list.remove(line);
+                    }
+                    self.save(&format!("Deleted {}.", line))?;
+                },
+                (Key::Char('u'), _) => {
+                    if self.list.undo(1) == 0 {
+                        self.message = String::from("Nothing to undo.");
+                    } else {
+                        self.save("Undone.")?;
+                    }
+                },
+                _ => {},
+            },
+        }
+        Ok(())
+    }
+
+    //enter applies the selected tag as the filter, the first entry clears it
+    fn handle_tag_key(&mut self, key : Key) {
+        match key {
+            Key::Char('q') => self.quit = true,
+            Key::Char('j') | Key::Down => self.selected_tag = (self.selected_tag + 1).min(self.tags.len()),
+            Key::Char('k') | Key::Up => self.selected_tag = self.selected_tag.saturating_sub(1),
+            Key::Tab | Key::Escape => self.mode = Mode::Tasks,
+            Key::Enter => {
+                let filter = match self.selected_tag {
+                    0 => String::new(),
+                    index => self.tags[index - 1].clone(),
+                };
+                self.set_filter(&filter);
+                self.mode = Mode::Tasks;
+            },
+            _ => {},
+        }
+    }
+
+    //the list follows the filter while it is typed, escape clears it
+    fn handle_filter_key(&mut self, key : Key) {
+        match key {
+            Key::Enter => self.mode = Mode::Tasks,
+            Key::Escape => {
+                self.set_filter("");
+                self.mode = Mode::Tasks;
+            },
+            key => {
+                if self.edit_input(key) {
+                    let filter = self.input.iter().collect::<String>();
+                    self.set_filter(&filter);
+                }
+            },
+        }
+    }
+
+    fn handle_edit_key(&mut self, key : Key, line : Option<usize>) -> io::Result<()> {
+        match key {
+            Key::Escape => self.mode = Mode::Tasks,
+            Key::Enter => {
+                self.mode = Mode::Tasks;
+                let text = self.input.iter().collect::<String>();
+                if text.trim().is_empty() {
+                    return Ok(());
+                }
+                let task = Task::new(text.trim());
+                match line {
+                    Some(line) => {
+                        self.list.replace(line, task);
+                        self.save(&format!("Replaced {}.", line))?;
+                    },
+                    None => {
+                        let line = self.list.add(task);
+                        self.save(&format!("Added {}.", line))?;
+                        self.select_line(line);
+                    },
+                }
+            },
+            key => {
+                self.edit_input(key);
+            },
+        }
+        Ok(())
+    }
+
+    //returns whether the input changed
+    fn edit_input(&mut self, key : Key) -> bool {
+        match key {
+            Key::Char(character) => {
+                self.input.insert(self.cursor, character);
+                self.cursor += 1;
+            },
+            Key::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                self.input.remove(self.cursor);
+            },
+            Key::Delete if self.cursor < self.input.len() => {
+                self.input.remove(self.cursor);
+            },
+            Key::Left => self.cursor = self.cursor.saturating_sub(1),
+            Key::Right => self.cursor = (self.cursor + 1).min(self.input.len()),
+            Key::Home => self.cursor = 0,
+            Key::End => self.cursor = self.input.len(),
+            _ => {},
+        }
+        matches!(key, Key::Char(_) | Key::Backspace | Key::Delete)
+    }
+
+    fn set_input(&mut self, text : &str) {
+        self.input = text.chars().collect();
+        self.cursor = self.input.len();
+    }
+
+    //an invalid filter keeps the previous list and shows the error
+    fn set_filter(&mut self, filter : &str) {
+        self.filter = String::from(filter);
+        if filter.trim().is_empty() {
+            self.query = None;
+        } else {
+            match Query::parse(filter) {
+                Ok(query) => self.query = Some(query),
+                Err(error) => {
+                    self.message = error.to_string();
+                    return;
+                },
+            }
+        }
+        self.message.clear();
+        self.update();
+    }
+
+    fn save(&mut self, message : &str) -> io::Result<()> {
+        let line = self.selected_line();
+        let report = self.list.sync()?;
+        self.report(&report, message);
+        if let Some(line) = line {
+            self.select_line(line);
+        }
+        Ok(())
+    }
+
+    fn report(&mut self, report : &SyncReport, message : &str) {
+        self.message = match report.conflicts.len() {
+            0 => String::from(message),
+            count => format!("{} The file was changed elsewhere, {} of your changes conflicted and were dropped.", message, count),
+        };
+        self.update();
+    }
+
+    //recomputes the shown tasks and the tags after the list or the filter changed
+    fn update(&mut self) {
+        let selected_line = self.selected_line();
+        let mut tasks = self.list.sectioned_tasks()
+            .filter(|&(_, section, task)| self.query.as_ref().is_none_or(|query| query.matches_in_section(task, section, self.today)))
+            .map(|(line, _, task)| (line, task))
+            .collect::<Vec<(usize, &Task)>>();
+        let spec = SortSpec::todo_sh();
+        tasks.sort_by(|left, right| spec.compare(left.1, right.1));
+        self.visible = tasks.into_iter().map(|(line, _)| line).collect();
+
+        let projects = self.list.tasks().flat_map(|(_, task)| task.projects()).map(|project| format!("+{}", project));
+        let contexts = self.list.tasks().flat_map(|(_, task)| task.contexts()).map(|context| format!("@{}", context));
+        self.tags = projects.collect::<BTreeSet<String>>().into_iter()
+            .chain(contexts.collect::<BTreeSet<String>>())
+            .collect();
+        self.selected_tag = self.selected_tag.min(self.tags.len());
+
+        match selected_line {
+            Some(line) => self.select_line(line),
+            None => self.select(self.selected as isize),
+        }
+    }
+
+    fn select(&mut self, index : isize) {
+        self.selected = index.clamp(0, self.visible.len().saturating_sub(1) as isize) as usize;
+    }
+
+    //keeps the selection if the task is still shown
+    fn select_line(&mut self, line : usize) {
+        match self.visible.iter().position(|&visible| visible == line) {
+            Some(index) => self.selected = index,
+            None => self.select(self.selected as isize),
+        }
+    }
+
+    /// The screen as lines of ANSI colored text: the tag pane and the tasks, then a status
+    /// line and a line for messages or the input.
+    pub fn draw(&mut self, width : usize, height : usize) -> Vec<String> {
+        let rows = height.saturating_sub(2);
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if rows > 0 && self.selected >= self.scroll + rows {
+            self.scroll = self.selected + 1 - rows;
+        }
+
+        let tag_width = self.tags.iter().map(|tag| tag.chars().count()).max().unwrap_or(0).max(3) + 2;
+        let tag_width = tag_width.min(width / 3);
+        let task_width = width.saturating_sub(tag_width + SEPARATOR.len());
+        let number_width = self.list.len().to_string().len();
+        let tag_scroll = (self.selected_tag + 1).saturating_sub(rows);
+
+        let mut screen = Vec::new();
+        for row in 0..rows {
+            let index = tag_scroll + row;
+            let tag = match index {
+                0 => Some(String::from("All")),
+                index => self.tags.get(index - 1).map(|tag| self.color_word(tag)),
+            };
+            let tag = tag.map(|tag| format!("{}{}", marker(self.mode == Mode::Tags && index == self.selected_tag), tag)).unwrap_or_default();
+
+            let index = self.scroll + row;
+            let task = self.visible.get(index)
+                .and_then(|&line| self.list.get(line).map(|task| (line, task)))
+                .map(|(line, task)| format!("{}{}", marker(self.mode != Mode::Tags && index == self.selected), self.renderer.render_numbered(line, number_width, task, self.today)))
+                .unwrap_or_default();
+            screen.push(format!("{}{}{}", fit(&tag, tag_width), SEPARATOR, fit(&task, task_width)).trim_end().to_string());
+        }
+
+        let filter = if self.filter.is_empty() { String::from("no filter") } else { format!("filter: {}", self.filter) };
+        screen.push(fit(&format!("{} of {} tasks, {}", self.visible.len(), self.list.tasks().count(), filter), width).trim_end().to_string());
+        let prompt = self.prompt();
+        screen.push(match prompt {
+            Some(ref prompt) => fit(&format!("{}{}", prompt, self.highlight_input()), width),
+            None => fit(&self.message, width),
+        });
+        screen
+    }
+
+    /// Where the terminal cursor belongs while typing, as column and row.
+    pub fn cursor(&self, height : usize) -> Option<(usize, usize)> {
+        self.prompt().map(|prompt| (prompt.chars().count() + self.cursor, height.saturating_sub(1)))
+    }
+
+    fn prompt(&self) -> Option<String> {
+        match self.mode {
+            Mode::Filter => Some(String::from("/")),
+            Mode::Edit(Some(line)) => Some(format!("Edit {}: ", line)),
+            Mode::Edit(None) => Some(String::from("New: ")),
+            _ => None,
+        }
+    }
+
+    //colors the words of the input like the components of a task, keeping the text as it is typed
+    fn highlight_input(&self) -> String {
+        let input = self.input.iter().collect::<String>();
+        if !self.renderer.colored || self.mode == Mode::Filter {
+            return input;
+        }
+        input.split(' ').map(|word| self.color_word(word)).collect::<Vec<String>>().join(" ")
+    }
+
+    fn color_word(&self, word : &str) -> String {
+        let theme = &self.renderer.theme;
+        let color = if word.len() > 1 && word.starts_with('+') {
+            theme.project.as_ref()
+        } else if word.len() > 1 && word.starts_with('@') {
+            theme.context.as_ref()
+        } else if word.len() == 3 && word.starts_with('(') && word.ends_with(')') {
+            word.chars().nth(1).and_then(|priority| theme.priorities.get(&priority).or(theme.other_priority.as_ref()))
+        } else if word.find(':').is_some_and(|colon| colon > 0 && colon < word.len() - 1) {
+            theme.key_value.as_ref()
+        } else {
+            None
+        };
+        match color {
+            Some(color) if self.renderer.colored => format!("{}{}{}", color, word, RESET),
+            _ => String::from(word),
+        }
+    }
+}
+
+fn marker(selected : bool) -> &'static str {
+    if selected { "> " } else { "  " }
+}
+
+/// Cuts ANSI colored text to `width` visible characters and pads it with spaces.
+/// Escape sequences don't count, colors are reset if the text was cut.
+fn fit(text : &str, width : usize) -> String {
+    let mut output = String::new();
+    let mut visible = 0;
+    let mut characters = text.chars();
+    while let Some(character) = characters.next() {
+        if character == '\x1b' {
+            output.push(character);
+            for character in characters.by_ref() {
+                output.push(character);
+                if character.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+            continue;
+        }
+        if visible == width {
+            if output.contains('\x1b') {
+                output.push_str(RESET);
+            }
+            return output;
+        }
+        output.push(character);
+        visible += 1;
+    }
+    output.push_str(&" ".repeat(width - visible));
+    output
+}
+
+/// Runs the interface on the todo file until it is quit.
+pub fn run(settings : &Settings, today : Date<Utc>) -> io::Result<()> {
+    let list = settings.config.todo_list()?;
+    //the interface draws on standard output, so the renderer already colors unless
+    //NO_COLOR, TODOTXT_PLAIN or -p say otherwise
+    let mut app = App::new(list, settings.renderer.clone(), today, settings.config.preserve_line_numbers);
+
+    let _terminal = Terminal::enter()?;
+    let mut stdout = io::stdout();
+    while !app.has_quit() {
+        let (width, height) = terminal::size()?;
+        let (width, height) = (width as usize, height as usize);
+        for (row, line) in app.draw(width, height).iter().enumerate() {
+            queue!(stdout, cursor::MoveTo(0, row as u16), terminal::Clear(terminal::ClearType::CurrentLine))?;
+            write!(stdout, "{}", line)?;
+        }
+        match app.cursor(height) {
+            Some((column, row)) => queue!(stdout, cursor::MoveTo(column as u16, row as u16), cursor::Show)?,
+            None => queue!(stdout, cursor::Hide)?,
+        }
+        stdout.flush()?;
+
+        //the file is checked for changes whenever no key was pressed for a second
+        if !event::poll(Duration::from_secs(1))? {
+            app.refresh()?;
+            continue;
+        }
+        if let Event::Key(event) = event::read()? {
+            if event.kind == KeyEventKind::Release {
+                continue;
+            }
+            let key = match event.code {
+                KeyCode::Char('c') if event.modifiers.contains(KeyModifiers::CONTROL) => {
+                    break;
+                },
+                KeyCode::Char(character) => Key::Char(character),
+                KeyCode::Enter => Key::Enter,
+                KeyCode::Esc => Key::Escape,
+                KeyCode::Backspace => Key::Backspace,
+                KeyCode::Delete => Key::Delete,
+                KeyCode::Tab | KeyCode::BackTab => Key::Tab,
+                KeyCode::Left => Key::Left,
+                KeyCode::Right => Key::Right,
+                KeyCode::Up => Key::Up,
+                KeyCode::Down => Key::Down,
+                KeyCode::PageUp => Key::PageUp,
+                KeyCode::PageDown => Key::PageDown,
+                KeyCode::Home => Key::Home,
+                KeyCode::End => Key::End,
+                _ => continue,
+            };
+            app.handle_key(key)?;
+        }
+    }
+    Ok(())
+}
+
+//puts the terminal into raw mode on the alternate screen until it is dropped
+struct Terminal;
+
+impl Terminal {
+    fn enter() -> io::Result<Terminal> {
+        terminal::enable_raw_mode()?;
+        queue!(io::stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
+        Ok(Terminal)
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let _ = queue!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = io::stdout().flush();
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tui::*;
+    use rustodo::Theme;
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+    use std::process;
+
+    fn todo_file(name : &str, todo : &str) -> PathBuf {
+        let directory = env::temp_dir().join(format!("rustodo-tui-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).expect("Must create test directory.");
+        let path = directory.join("todo.txt");
+        fs::write(&path, todo).expect("Must write todo.txt.");
+        path
+    }
+
+    fn app(path : &PathBuf) -> App {
+        let list = TaskList::load(path).expect("Must load todo.txt.");
+        App::new(list, Renderer::new(Theme::default(), false), Utc.ymd(2018, 11, 24), false)
+    }
+
+    fn type_text(app : &mut App, text : &str) {
+        for character in text.chars() {
+            app.handle_key(Key::Char(character)).expect("Must handle key.");
+        }
+    }
+
+    #[test]
+    fn draws_tags_next_to_sorted_tasks() {
+        let path = todo_file("draw", "Write report +work\n(A) Call mom @phone\n");
+        let screen = app(&path).draw(40, 5);
+
+        assert_eq!(screen, vec![
+            String::from("  All    | > 2 (A) Call mom @phone"),
+            String::from("  +work  |   1 Write report +work"),
+            String::from("  @phone |"),
+            String::from("2 of 2 tasks, no filter"),
+            format!("{:40}", "Press ? for help."),
+        ]);
+    }
+
+    #[test]
+    fn filters_while_typing_and_from_the_tag_pane() {
+        let path = todo_file("filter", "Write report +work\n(A) Call mom @phone\n");
+        let mut app = app(&path);
+
+        app.handle_key(Key::Char('/')).expect("Must handle key.");
+        type_text(&mut app, "+work");
+        assert_eq!(app.draw(40, 4)[2], "1 of 2 tasks, filter: +work");
+        type_text(&mut app, " and (");
+        assert!(app.draw(40, 4)[3].starts_with("/+work and ("));
+        assert_eq!(app.selected_line(), Some(1));
+        app.handle_key(Key::Escape).expect("Must handle key.");
+
+        app.handle_key(Key::Tab).expect("Must handle key.");
+        app.handle_key(Key::Down).expect("Must handle key.");
+        app.handle_key(Key::Down).expect("Must handle key.");
+        app.handle_key(Key::Enter).expect("Must handle key.");
+        assert_eq!(app.selected_line(), Some(2));
+        assert_eq!(app.draw(40, 4)[2], "1 of 2 tasks, filter: @phone");
+    }
+
+    #[test]
+    fn single_keys_edit_and_save_the_selected_task() {
+        let path = todo_file("keys", "Write report +work\nCall mom\n");
+        let mut app = app(&path);
+
+        app.handle_key(Key::Char('B')).expect("Must handle key.");
+        assert_eq!(fs::read_to_string(&path).expect("Must read."), "Write report +work\n(B) Call mom\n");
+        app.handle_key(Key::Char('x')).expect("Must handle key.");
+        assert_eq!(fs::read_to_string(&path).expect("Must read."), "Write report +work\nx 2018-11-24 2018-11-24 Call mom\n");
+        app.handle_key(Key::Char('u')).expect("Must handle key.");
+        assert_eq!(fs::read_to_string(&path).expect("Must read."), "Write report +work\n(B) Call mom\n");
+
+        app.handle_key(Key::Char('e')).expect("Must handle key.");
+        app.handle_key(Key::Home).expect("Must handle key.");
+        (0..4).for_each(|_| app.handle_key(Key::Delete).expect("Must handle key."));
+        app.handle_key(Key::End).expect("Must handle key.");
+        type_text(&mut app, " @phone");
+        assert_eq!(app.cursor(4), Some((23, 3)));
+        app.handle_key(Key::Enter).expect("Must handle key.");
+
+        app.handle_key(Key::Char('n')).expect("Must handle key.");
+        type_text(&mut app, "Pay rent");
+        app.handle_key(Key::Enter).expect("Must handle key.");
+        assert_eq!(fs::read_to_string(&path).expect("Must read."), "Write report +work\nCall mom @phone\nPay rent\n");
+        assert_eq!(app.selected_line(), Some(3));
+    }
+
+    #[test]
+    fn external_changes_are_picked_up_and_merged() {
+        let path = todo_file("external", "Write report\nCall mom\n");
+        let mut app = app(&path);
+        fs::write(&path, "Pay rent\nWrite report\nCall mom\n").expect("Must write.");
+
+        app.refresh().expect("Must refresh.");
+        assert_eq!(app.draw(40, 3)[1], "3 of 3 tasks, no filter");
+
+        fs::write(&path, "Pay rent\nWrite report\n").expect("Must write.");
+        app.handle_key(Key::Home).expect("Must handle key.");
+        assert_eq!(app.selected_line(), Some(3));
+        app.handle_key(Key::Char('A')).expect("Must handle key.");
+        assert_eq!(fs::read_to_string(&path).expect("Must read."), "Pay rent\nWrite report\n");
+        assert!(app.draw(80, 3)[2].contains("1 of your changes conflicted"));
+    }
+
+    #[test]
+    fn fit_ignores_escape_sequences() {
+        assert_eq!(fit("\x1b[1;33mPay rent\x1b[0m", 3), "\x1b[1;33mPay\x1b[0m");
+        assert_eq!(fit("\x1b[1;33mPay\x1b[0m", 5), "\x1b[1;33mPay\x1b[0m  ");
+    }
+}
@@ -0,0 +1,409 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Error;
+use std::fs;
+use std::io;
+use std::iter::Peekable;
+use std::path::Path;
+use std::path::PathBuf;
+use std::str::Chars;
+use toml;
+use task_list::TaskList;
+
+pub type Variables = BTreeMap<String, String>;
+
+/// The settings of todo.sh: where the lists are and how they are edited.
+///
+/// Read either from a todo.sh config like `~/.todo/config`, which is a shell script
+/// of which the `export VAR=value` subset is understood, or from the equivalent TOML:
+///
+/// ```toml
+/// todo_dir = "~/.todo"
+/// date_on_add = true
+///
+/// [variables]
+/// PRI_A = "yellow"
+/// ```
+///
+/// Like in todo.sh, `TODOTXT_*` environment variables override the config file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    pub todo_dir: PathBuf,
+    pub todo_file: PathBuf,
+    pub done_file: PathBuf,
+    pub report_file: PathBuf,
+    pub date_on_add: bool,
+    pub preserve_line_numbers: bool,
+    /// Where todo.sh add-ons are, `TODO_ACTIONS_DIR` or `~/.todo/actions`, falling back
+    /// to `~/.todo.actions.d` if that is a directory and the other isn't.
+    pub actions_dir: PathBuf,
+    /// All variables of the config, e.g. colors, with the `TODOTXT_*` overrides applied.
+    pub variables: Variables,
+    /// The file the config was loaded from. TOML configs hold further tables like `views`.
+    pub path: Option<PathBuf>,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    Toml(toml::de::Error),
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
+        match self {
+            ConfigError::Io(error) => write!(formatter, "Couldn't read the config: {}", error),
+            ConfigError::Toml(error) => write!(formatter, "Couldn't parse the config: {}", error),
+        }
+    }
+}
+
+impl From<io::Error> for ConfigError {
+    fn from(error : io::Error) -> ConfigError {
+        ConfigError::Io(error)
+    }
+}
+
+impl From<toml::de::Error> for ConfigError {
+    fn from(error : toml::de::Error) -> ConfigError {
+        ConfigError::Toml(error)
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct TomlConfig {
+    todo_dir: Option<String>,
+    todo_file: Option<String>,
+    done_file: Option<String>,
+    report_file: Option<String>,
+    actions_dir: Option<String>,
+    date_on_add: Option<bool>,
+    preserve_line_numbers: Option<bool>,
+    variables: Variables,
+}
+
+impl Config {
+    /// The config from the environment alone, e.g. `TODO_DIR` and `TODOTXT_DATE_ON_ADD`.
+    pub fn from_environment() -> Config {
+        Config::from_variables(Variables::new(), &environment())
+    }
+
+    /// Parses a todo.sh config. Assignments may use quotes, `~` and `$VAR`, `${VAR}`
+    /// or `${VAR:-default}`, variables are looked up in the config first and in
+    /// `environment` second. Commands are not run, except that `$(dirname "$0")`
+    /// is the directory of the config as todo.sh sits next to it. Other lines are ignored.
+    pub fn parse_shell(input : &str, directory : Option<&Path>, environment : &Variables) -> Config {
+        let mut variables = Variables::new();
+        for line in input.lines() {
+            let line = line.trim();
+            let line = line.strip_prefix("export ").map(str::trim_start).unwrap_or(line);
+            let name_length = line.find(|character : char| !(character.is_ascii_alphanumeric() || character == '_')).unwrap_or(line.len());
+            if name_length == 0 || !line[name_length..].starts_with('=') || line.starts_with(|character : char| character.is_ascii_digit()) {
+                continue;
+            }
+            let value = {
+                let mut expander = ShellExpander { variables: &variables, environment, directory };
+                expander.value(&mut line[name_length + 1..].chars().peekable())
+            };
+            variables.insert(String::from(&line[..name_length]), value);
+        }
+        Config::from_variables(variables, environment)
+    }
+
+    pub fn parse_toml(input : &str, environment : &Variables) -> Result<Config, ConfigError> {
+        let config : TomlConfig = toml::from_str(input)?;
+        let mut variables = config.variables;
+        let paths = [("TODO_DIR", config.todo_dir), ("TODO_FILE", config.todo_file), ("DONE_FILE", config.done_file), ("REPORT_FILE", config.report_file),
+            ("TODO_ACTIONS_DIR", config.actions_dir)];
+        for (name, path) in paths.iter() {
+            if let Some(path) = path {
+                variables.insert(String::from(*name), expand_home(path, environment));
+            }
+        }
+        let flags = [("TODOTXT_DATE_ON_ADD", config.date_on_add), ("TODOTXT_PRESERVE_LINE_NUMBERS", config.preserve_line_numbers)];
+        for (name, flag) in flags.iter() {
+            if let Some(flag) = flag {
+                variables.insert(String::from(*name), String::from(if *flag { "1" } else { "0" }));
+            }
+        }
+        Ok(Config::from_variables(variables, environment))
+    }
+
+    /// Loads a config, files ending in `.toml` are TOML, all others todo.sh configs.
+    pub fn load<P: AsRef<Path>>(path : P) -> Result<Config, ConfigError> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)?;
+        let mut config = if is_toml(path) {
+            Config::parse_toml(&content, &environment())?
+        } else {
+            Config::parse_shell(&content, path.parent(), &environment())
+        };
+        config.path = Some(path.to_path_buf());
+        Ok(config)
+    }
+
+    /// Loads the config from where todo.sh looks for it: `TODOTXT_CFG_FILE`, `~/.todo/config`,
+    /// `~/todo.cfg`, `~/.todo.cfg` and `~/.config/todo/config`. `~/.todo/config.toml` is
+    /// checked first. Without a config file the environment is used.
+    pub fn load_default() -> Result<Config, ConfigError> {
+        let environment = environment();
+        let mut candidates = environment.get("TODOTXT_CFG_FILE").map(PathBuf::from).into_iter().collect::<Vec<PathBuf>>();
+        if let Some(home) = environment.get("HOME").map(PathBuf::from) {
+            candidates.extend([".todo/config.toml", ".todo/config", "todo.cfg", ".todo.cfg", ".config/todo/config"].iter().map(|name| home.join(name)));
+        }
+        match candidates.into_iter().find(|path| path.is_file()) {
+            Some(path) => Config::load(path),
+            None => Ok(Config::from_environment()),
+        }
+    }
+
+    fn from_variables(mut variables : Variables, environment : &Variables) -> Config {
+        for (name, value) in environment {
+            if name.starts_with("TODOTXT_") {
+                variables.insert(name.clone(), value.clone());
+            }
+        }
+        let lookup = |name : &str| variables.get(name).or_else(|| environment.get(name)).filter(|value| !value.is_empty());
+        let todo_dir = lookup("TODO_DIR").map(PathBuf::from)
+            .or_else(|| environment.get("HOME").map(|home| Path::new(home).join(".todo")))
+            .unwrap_or_else(|| PathBuf::from("."));
+        let file = |name : &str, file_name : &str| lookup(name).map(PathBuf::from).unwrap_or_else(|| todo_dir.join(file_name));
+        let flag = |name : &str, default : bool| match lookup(name).map(String::as_str) {
+            Some("1") => true,
+            Some("0") => false,
+            _ => default,
+        };
+        let home = |name : &str| environment.get("HOME").map(|home| Path::new(home).join(name)).unwrap_or_else(|| todo_dir.join(name));
+        let actions_dir = lookup("TODO_ACTIONS_DIR").map(PathBuf::from)
+            .unwrap_or_else(|| home(".todo/actions"));
+        let actions_dir = match home(".todo.actions.d") {
+            alternative if !actions_dir.is_dir() && alternative.is_dir() => alternative,
+            _ => actions_dir,
+        };
+        Config {
+            todo_file: file("TODO_FILE", "todo.txt"),
+            done_file: file("DONE_FILE", "done.txt"),
+            report_file: file("REPORT_FILE", "report.txt"),
+            date_on_add: flag("TODOTXT_DATE_ON_ADD", false),
+            preserve_line_numbers: flag("TODOTXT_PRESERVE_LINE_NUMBERS", true),
+            actions_dir,
+            todo_dir,
+            variables,
+            path: None,
+        }
+    }
+
+    pub fn variable(&self, name : &str) -> Option<&str> {
+        self.variables.get(name).map(String::as_str)
+    }
+
+    /// A todo.sh style flag, `1` is true and `0` is false.
+    pub fn flag(&self, name : &str, default : bool) -> bool {
+        match self.variable(name) {
+            Some("1") => true,
+            Some("0") => false,
+            _ => default,
+        }
+    }
+
+    /// The config file if it is a TOML config.
+    pub fn toml_path(&self) -> Option<&Path> {
+        self.path.as_deref().filter(|path| is_toml(path))
+    }
+
+    /// Loads the todo file, an empty list if it doesn't exist yet.
+    pub fn todo_list(&self) -> io::Result<TaskList> {
+        load_or_create(&self.todo_file)
+    }
+
+    /// Loads the done file, an empty list if it doesn't exist yet.
+    pub fn done_list(&self) -> io::Result<TaskList> {
+        load_or_create(&self.done_file)
+    }
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config::from_environment()
+    }
+}
+
+fn is_toml(path : &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "toml")
+}
+
+fn environment() -> Variables {
+    env::vars().collect()
+}
+
+fn load_or_create(path : &Path) -> io::Result<TaskList> {
+    match TaskList::load(path) {
+        Err(ref error) if error.kind() == io::ErrorKind::NotFound => Ok(TaskList::new(path)),
+        result => result,
+    }
+}
+
+fn expand_home(path : &str, environment : &Variables) -> String {
+    match (path.strip_prefix('~'), environment.get("HOME")) {
+        (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with('/') => format!("{}{}", home, rest),
+        _ => String::from(path),
+    }
+}
+
+struct ShellExpander<'a> {
+    variables: &'a Variables,
+    environment: &'a Variables,
+    directory: Option<&'a Path>,
+}
+
+impl<'a> ShellExpander<'a> {
+    fn lookup(&self, name : &str) -> String {
+        self.variables.get(name).or_else(|| self.environment.get(name)).cloned().unwrap_or_default()
+    }
+
+    /// The value of an assignment, up to unquoted whitespace or a `;`.
+    fn value(&mut self, characters : &mut Peekable<Chars>) -> String {
+        let mut value = String::new();
+        if characters.peek() == Some(&'~') {
+            characters.next();
+            value.push_str(&self.lookup("HOME"));
+        }
+        while let Some(character) = characters.next() {
+            match character {
+                '\'' => value.extend(characters.by_ref().take_while(|&character| character != '\'')),
+                '"' => self.double_quoted(characters, &mut value),
+                '\\' => value.extend(characters.next()),
+                '$' => value.push_str(&self.expansion(characters)),
+                ';' => break,
+                character if character.is_whitespace() => break,
+                character => value.push(character),
+            }
+        }
+        value
+    }
+
+    fn double_quoted(&mut self, characters : &mut Peekable<Chars>, value : &mut String) {
+        while let Some(character) = characters.next() {
+            match character {
+                '"' => return,
+                '\\' => match characters.peek() {
+                    Some(&escaped) if "\"\\$`".contains(escaped) => {
+                        value.push(escaped);
+                        characters.next();
+                    },
+                    _ => value.push('\\'),
+                },
+                '$' => value.push_str(&self.expansion(characters)),
+                character => value.push(character),
+            }
+        }
+    }
+
+    /// Expands what follows a `$`.
+    fn expansion(&mut self, characters : &mut Peekable<Chars>) -> String {
+        match characters.peek() {
+            Some('{') => {
+                characters.next();
+                let inner = characters.by_ref().take_while(|&character| character != '}').collect::<String>();
+                match inner.find(":-") {
+                    Some(position) => {
+                        let value = self.lookup(&inner[..position]);
+                        if value.is_empty() {
+                            self.value(&mut inner[position + 2..].chars().peekable())
+                        } else {
+                            value
+                        }
+                    },
+                    None => self.lookup(&inner),
+                }
+            },
+            Some('(') => {
+                characters.next();
+                let command = characters.by_ref().take_while(|&character| character != ')').collect::<String>();
+                match (command.split_whitespace().collect::<Vec<&str>>().as_slice(), self.directory) {
+                    (["dirname", "\"$0\""], Some(directory)) | (["dirname", "$0"], Some(directory)) => directory.display().to_string(),
+                    _ => String::new(),
+                }
+            },
+            _ => {
+                let mut name = String::new();
+                while let Some(&character) = characters.peek() {
+                    if !(character.is_ascii_alphanumeric() || character == '_') {
+                        break;
+                    }
+                    name.push(character);
+                    characters.next();
+                }
+                if name.is_empty() { String::from("$") } else { self.lookup(&name) }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use config::*;
+
+    fn environment(pairs : &[(&str, &str)]) -> Variables {
+        pairs.iter().map(|&(name, value)| (String::from(name), String::from(value))).collect()
+    }
+
+    #[test]
+    fn parses_the_todo_sh_default_config() {
+        let input = r#"
+# Your todo/done/report.txt locations
+export TODO_DIR=$(dirname "$0")
+export TODO_FILE="$TODO_DIR/todo.txt"
+export DONE_FILE="$TODO_DIR/done.txt"
+export REPORT_FILE="$TODO_DIR/report.txt"
+
+export YELLOW='\\033[0;33m'  # a color
+export PRI_A=$YELLOW
+if [ -z "$TODO_ACTIONS_DIR" ]; then echo "not shell"; fi
+"#;
+        let config = Config::parse_shell(input, Some(Path::new("/opt/todo")), &Variables::new());
+
+        assert_eq!(config.todo_dir, PathBuf::from("/opt/todo"));
+        assert_eq!(config.todo_file, PathBuf::from("/opt/todo/todo.txt"));
+        assert_eq!(config.report_file, PathBuf::from("/opt/todo/report.txt"));
+        assert_eq!(config.variable("PRI_A"), Some(r"\\033[0;33m"));
+        assert_eq!(config.variables.len(), 6);
+    }
+
+    #[test]
+    fn expands_variables_defaults_and_home() {
+        let input = "TODO_DIR=${TODOS:-~/notes}\nexport TODO_FILE=~/\"my todos\"/todo.txt;\nDONE_FILE=$TODO_DIR/${NAME}.txt\nTODOTXT_DATE_ON_ADD=1\n";
+        let config = Config::parse_shell(input, None, &environment(&[("HOME", "/home/max"), ("NAME", "finished")]));
+
+        assert_eq!(config.todo_dir, PathBuf::from("/home/max/notes"));
+        assert_eq!(config.todo_file, PathBuf::from("/home/max/my todos/todo.txt"));
+        assert_eq!(config.done_file, PathBuf::from("/home/max/notes/finished.txt"));
+        assert!(config.date_on_add);
+        assert!(config.preserve_line_numbers);
+        assert_eq!(config.actions_dir, PathBuf::from("/home/max/.todo/actions"));
+    }
+
+    #[test]
+    fn environment_fills_in_and_overrides_flags() {
+        let config = Config::parse_shell("TODOTXT_PRESERVE_LINE_NUMBERS=1\n", None,
+            &environment(&[("TODO_DIR", "/srv/todo"), ("TODOTXT_PRESERVE_LINE_NUMBERS", "0")]));
+
+        assert_eq!(config.todo_file, PathBuf::from("/srv/todo/todo.txt"));
+        assert!(!config.preserve_line_numbers);
+        assert!(!config.flag("TODOTXT_PRESERVE_LINE_NUMBERS", true));
+    }
+
+    #[test]
+    fn toml_configs_are_equivalent() {
+        let input = "todo_dir = \"~/notes\"\nactions_dir = \"~/addons\"\ndate_on_add = true\npreserve_line_numbers = false\n\n[variables]\nPRI_A = \"yellow\"\n\n[views.today]\nquery = \"due<=today\"\n";
+        let config = Config::parse_toml(input, &environment(&[("HOME", "/home/max")])).expect("Must parse.");
+        let shell = Config::parse_shell("TODO_DIR=~/notes\nTODO_ACTIONS_DIR=~/addons\nTODOTXT_DATE_ON_ADD=1\nTODOTXT_PRESERVE_LINE_NUMBERS=0\nPRI_A=yellow\n", None,
+            &environment(&[("HOME", "/home/max")]));
+
+        assert_eq!(config, shell);
+        assert!(Config::parse_toml("date_on_add = \"yes\"", &Variables::new()).is_err());
+    }
+}
@@ -1,4 +1,5 @@
 use regex::Regex;
+use std::ops::Range;
 use std::fmt::Display;
 use std::fmt::Formatter;
 use std::fmt::Error;
@@ -153,6 +154,19 @@ pub fn description_components_to_string(components: &Vec<DescriptionComponent>)
         .collect()
 }
 
+/// The byte ranges the components take up in the description string.
+pub fn description_component_spans(components: &[DescriptionComponent]) -> Vec<Range<usize>> {
+    let mut start = 0;
+    components.iter()
+        .map(|component| {
+            let end = start + component.to_string().len();
+            let span = start..end;
+            start = end;
+            span
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use description_component::*;
@@ -294,4 +308,14 @@ mod tests {
 
         assert_eq!(description_components_to_string(&components), description);
     }
+
+    #[test]
+    fn component_spans_cover_the_description() {
+        let description = "Call +mom @phone due:2018-11-24 ";
+        let components = DescriptionComponentsParser::parse(description).expect("Must parse!").value;
+        let spans = description_component_spans(&components);
+
+        assert_eq!(spans, vec![0..5, 5..9, 9..10, 10..16, 16..17, 17..31, 31..32]);
+        assert_eq!(&description[spans[1].clone()], "+mom");
+    }
 }
@@ -0,0 +1,363 @@
+use chrono::prelude::*;
+use regex;
+use regex::Regex;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Error;
+use description_component::DescriptionComponent;
+use task::Task;
+use task_list::TaskList;
+
+/// A change applied to every task of a bulk edit.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EditOperation {
+    SetPriority(Option<char>),
+    /// Marks the task as completed, see `Task::complete`.
+    Complete(Date<Utc>),
+    RenameProject { from: String, to: String },
+    RenameContext { from: String, to: String },
+    AddProject(String),
+    RemoveProject(String),
+    AddContext(String),
+    RemoveContext(String),
+    /// Sets the value of an option, the option is appended if the task doesn't have it yet.
+    SetOption(String, String),
+    RemoveOption(String),
+    Append(String),
+    Prepend(String),
+    Replace(Replacement),
+}
+
+impl EditOperation {
+    pub fn apply(&self, task : &mut Task) {
+        match self {
+            EditOperation::SetPriority(priority) => task.priority = *priority,
+            EditOperation::Complete(date) => task.complete(*date),
+            EditOperation::RenameProject { from, to } => map_components(task, |component| match component {
+                DescriptionComponent::Project(ref project) if project == from => Some(DescriptionComponent::Project(to.clone())),
+                component => Some(component),
+            }),
+            EditOperation::RenameContext { from, to } => map_components(task, |component| match component {
+                DescriptionComponent::Context(ref context) if context == from => Some(DescriptionComponent::Context(to.clone())),
+                component => Some(component),
+            }),
+            EditOperation::AddProject(project) => {
+                if !task.projects().contains(project) {
+                    append(task, &format!("+{}", project));
+                }
+            },
+            EditOperation::AddContext(context) => {
+                if !task.contexts().contains(context) {
+                    append(task, &format!("@{}", context));
+                }
+            },
+            EditOperation::RemoveProject(name) => map_components(task, |component| match component {
+                DescriptionComponent::Project(ref project) if project == name => None,
+                component => Some(component),
+            }),
+            EditOperation::RemoveContext(name) => map_components(task, |component| match component {
+                DescriptionComponent::Context(ref context) if context == name => None,
+                component => Some(component),
+            }),
+            EditOperation::SetOption(key, value) => {
+                if task.options().contains_key(key) {
+                    map_components(task, |component| match component {
+                        DescriptionComponent::KeyValue(ref option_key, _) if option_key == key => Some(DescriptionComponent::KeyValue(key.clone(), value.clone())),
+                        component => Some(component),
+                    })
+                } else {
+                    append(task, &format!("{}:{}", key, value));
+                }
+            },
+            EditOperation::RemoveOption(key) => map_components(task, |component| match component {
+                DescriptionComponent::KeyValue(ref option_key, _) if option_key == key => None,
+                component => Some(component),
+            }),
+            EditOperation::Append(text) => append(task, text),
+            EditOperation::Prepend(text) => {
+                let description = format!("{} {}", text, task.description());
+                task.set_description(description.trim());
+            },
+            EditOperation::Replace(replacement) => {
+                replacement.apply(task);
+            },
+        }
+    }
+}
+
+/// The components of a description a `Replacement` may change.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReplaceScope {
+    pub text: bool,
+    pub projects: bool,
+    pub contexts: bool,
+    /// Only the values of options, keys are never replaced.
+    pub options: bool,
+}
+
+impl Default for ReplaceScope {
+    /// Only text, so tags like `+foobar` survive replacing `foo`.
+    fn default() -> ReplaceScope {
+        ReplaceScope {
+            text: true,
+            projects: false,
+            contexts: false,
+            options: false,
+        }
+    }
+}
+
+/// A regex search and replace on descriptions that, unlike sed on the file, only
+/// touches the components in its scope.
+///
+/// The replacement may use `$1` or `$name` to refer to capture groups. The result
+/// is parsed again, so e.g. text replaced with `+project` becomes a project.
+#[derive(Debug, Clone)]
+pub struct Replacement {
+    pattern: Regex,
+    replacement: String,
+    scope: ReplaceScope,
+}
+
+impl PartialEq for Replacement {
+    fn eq(&self, other : &Replacement) -> bool {
+        self.pattern.as_str() == other.pattern.as_str() && self.replacement == other.replacement && self.scope == other.scope
+    }
+}
+
+impl Replacement {
+    pub fn new(pattern : &str, replacement : &str, scope : ReplaceScope) -> Result<Replacement, regex::Error> {
+        Ok(Replacement {
+            pattern: Regex::new(pattern)?,
+            replacement: String::from(replacement),
+            scope,
+        })
+    }
+
+    /// Replaces all matches in the components in scope and returns whether the task changed.
+    pub fn apply(&self, task : &mut Task) -> bool {
+        let replace = |value : &str| self.pattern.replace_all(value, self.replacement.as_str()).into_owned();
+        let description = task.description_components().iter()
+            .map(|component| match component {
+                DescriptionComponent::Text(text) if self.scope.text => replace(text),
+                DescriptionComponent::Project(project) if self.scope.projects => format!("+{}", replace(project)),
+                DescriptionComponent::Context(context) if self.scope.contexts => format!("@{}", replace(context)),
+                DescriptionComponent::KeyValue(key, value) if self.scope.options => format!("{}:{}", key, replace(value)),
+                component => component.to_string(),
+            })
+            .collect::<String>();
+        if description == task.description() {
+            return false;
+        }
+        task.set_description(&description);
+        true
+    }
+}
+
+/// A changed line, the line numbers are the same before and after the edit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineChange {
+    pub line: usize,
+    pub old: String,
+    pub new: String,
+}
+
+/// The changes of a bulk edit, ordered by line.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Diff {
+    pub changes: Vec<LineChange>,
+}
+
+impl Diff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+}
+
+impl Display for Diff {
+    /// Two lines per change, the old one prefixed with `-` and the new one with `+`,
+    /// each followed by the line number.
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
+        for change in &self.changes {
+            writeln!(formatter, "-{} {}", change.line, change.old)?;
+            writeln!(formatter, "+{} {}", change.line, change.new)?;
+        }
+        Ok(())
+    }
+}
+
+/// Applies the operations in order to every task matching the filter, e.g. a
+/// compiled `Query`, and returns the changed lines.
+///
+/// In a dry run the list is left untouched. Otherwise the tasks are replaced through
+/// `TaskList::replace`, so every change is journaled and can be undone. Like all
+/// other edits, nothing is written until the list is saved.
+pub fn bulk_edit<F>(list : &mut TaskList, filter : F, operations : &[EditOperation], dry_run : bool) -> Diff
+    where F: Fn(&Task) -> bool {
+    let edited = list.tasks()
+        .filter(|&(_, task)| filter(task))
+        .filter_map(|(line, task)| {
+            let mut edited = task.clone();
+            for operation in operations {
+                operation.apply(&mut edited);
+            }
+            let change = LineChange { line, old: task.to_string(), new: edited.to_string() };
+            if change.old == change.new { None } else { Some((change, edited)) }
+        })
+        .collect::<Vec<(LineChange, Task)>>();
+
+    let mut diff = Diff::default();
+    for (mut change, task) in edited {
+        if !dry_run {
+            list.replace(change.line, task);
+            change.new = list.get(change.line).map(|task| task.to_string()).unwrap_or_default();
+        }
+        diff.changes.push(change);
+    }
+    diff
+}
+
+fn map_components<F>(task : &mut Task, mut mapping : F)
+    where F: FnMut(DescriptionComponent) -> Option<DescriptionComponent> {
+    let mut changed = false;
+    let mut removed = false;
+    let mut description = String::new();
+    for component in task.description_components() {
+        let text = match mapping(component.clone()) {
+            Some(mapped) => {
+                changed |= mapped != *component;
+                mapped.to_string()
+            },
+            None => {
+                changed = true;
+                removed = true;
+                continue;
+            },
+        };
+        //the whitespace around a removed component becomes a single space, other separators stay
+        if removed {
+            description.truncate(description.trim_end().len());
+            if !description.is_empty() && !text.trim_start().is_empty() {
+                description.push(' ');
+            }
+            description.push_str(text.trim_start());
+            removed = false;
+        } else {
+            description.push_str(&text);
+        }
+    }
+    if removed {
+        description.truncate(description.trim_end().len());
+    }
+    if changed {
+        task.set_description(&description);
+    }
+}
+
+fn append(task : &mut Task, text : &str) {
+    let description = format!("{} {}", task.description(), text);
+    task.set_description(description.trim());
+}
+
+#[cfg(test)]
+mod tests {
+    use edit::*;
+
+    fn edited(line : &str, operation : EditOperation) -> String {
+        let mut task = Task::new(line);
+        operation.apply(&mut task);
+        task.to_string()
+    }
+
+    #[test]
+    fn operations_without_effect_keep_the_spacing() {
+        assert_eq!(edited("Fix  bug +rustodo", EditOperation::RenameProject { from: String::from("other"), to: String::from("new") }), "Fix  bug +rustodo");
+        assert_eq!(edited("Call  mom @phone", EditOperation::RemoveContext(String::from("office"))), "Call  mom @phone");
+        assert_eq!(edited("Pay  rent due:2018-11-30", EditOperation::SetOption(String::from("due"), String::from("2018-11-30"))), "Pay  rent due:2018-11-30");
+
+        let mut list = TaskList::parse("todo.txt", "Fix  bug +rustodo\n");
+        let diff = bulk_edit(&mut list, |_| true, &[EditOperation::RemoveProject(String::from("other"))], false);
+        assert!(diff.is_empty());
+        assert_eq!(list.to_string(), "Fix  bug +rustodo\n");
+    }
+
+    #[test]
+    fn operations_keep_the_spacing_of_other_components() {
+        assert_eq!(edited("Fix  bug +oldname  @office", EditOperation::RenameProject { from: String::from("oldname"), to: String::from("newname") }),
+            "Fix  bug +newname  @office");
+        assert_eq!(edited("Call  mom @waiting   for news", EditOperation::RemoveContext(String::from("waiting"))), "Call  mom for news");
+        assert_eq!(edited("@waiting Call  mom", EditOperation::RemoveContext(String::from("waiting"))), "Call  mom");
+        assert_eq!(edited("Pay  rent due:2018-11-30", EditOperation::RemoveOption(String::from("due"))), "Pay  rent");
+    }
+
+    #[test]
+    fn operations_edit_components() {
+        assert_eq!(edited("Fix bug +oldname @office", EditOperation::RenameProject { from: String::from("oldname"), to: String::from("newname") }),
+            "Fix bug +newname @office");
+        assert_eq!(edited("Fix bug +oldnamebar", EditOperation::RenameProject { from: String::from("oldname"), to: String::from("newname") }),
+            "Fix bug +oldnamebar");
+        assert_eq!(edited("Call mom @waiting for news", EditOperation::RemoveContext(String::from("waiting"))), "Call mom for news");
+        assert_eq!(edited("Call mom @phone", EditOperation::AddContext(String::from("phone"))), "Call mom @phone");
+        assert_eq!(edited("Pay rent due:2018-11-30 +home", EditOperation::SetOption(String::from("due"), String::from("2018-12-01"))),
+            "Pay rent due:2018-12-01 +home");
+        assert_eq!(edited("Pay rent", EditOperation::SetOption(String::from("due"), String::from("2018-12-01"))), "Pay rent due:2018-12-01");
+        assert_eq!(edited("(A) 2018-11-20 Pay rent", EditOperation::Complete(Utc.ymd(2018, 11, 24))), "x 2018-11-24 2018-11-20 Pay rent");
+        assert_eq!(edited("(B) Pay rent", EditOperation::Complete(Utc.ymd(2018, 11, 24))), "x 2018-11-24 2018-11-24 Pay rent");
+    }
+
+    #[test]
+    fn replacements_only_touch_components_in_scope() {
+        let replaced = |line : &str, scope : ReplaceScope| {
+            let mut task = Task::new(line);
+            Replacement::new("foo", "baz", scope).expect("Must compile.").apply(&mut task);
+            task.to_string()
+        };
+        let line = "foo the +foobar @foo ref:foo1";
+
+        assert_eq!(replaced(line, ReplaceScope::default()), "baz the +foobar @foo ref:foo1");
+        assert_eq!(replaced(line, ReplaceScope { projects: true, ..ReplaceScope::default() }), "baz the +bazbar @foo ref:foo1");
+        assert_eq!(replaced(line, ReplaceScope { text: false, contexts: true, options: true, ..ReplaceScope::default() }),
+            "foo the +foobar @baz ref:baz1");
+    }
+
+    #[test]
+    fn replaced_descriptions_are_parsed_again() {
+        let mut task = Task::new("Call Bob about taxes");
+        let replacement = Replacement::new(r"about (\w+)", "+$1", ReplaceScope::default()).expect("Must compile.");
+
+        assert!(replacement.apply(&mut task));
+        assert_eq!(task.projects(), vec!["taxes"]);
+        assert!(!replacement.apply(&mut task));
+    }
+
+    #[test]
+    fn bulk_edit_changes_matching_tasks() {
+        let mut list = TaskList::parse("todo.txt", "Call mom @waiting\n(A) Write docs\nAsk Bob @waiting\n");
+        let operations = [EditOperation::SetPriority(Some('C'))];
+        let diff = bulk_edit(&mut list, |task : &Task| task.contexts().contains(&String::from("waiting")), &operations, false);
+
+        assert_eq!(diff.to_string(), "-1 Call mom @waiting\n+1 (C) Call mom @waiting\n-3 Ask Bob @waiting\n+3 (C) Ask Bob @waiting\n");
+        assert_eq!(list.to_string(), "(C) Call mom @waiting\n(A) Write docs\n(C) Ask Bob @waiting\n");
+        assert_eq!(list.undo(2), 2);
+        assert_eq!(list.to_string(), "Call mom @waiting\n(A) Write docs\nAsk Bob @waiting\n");
+    }
+
+    #[test]
+    fn dry_run_leaves_the_list_untouched() {
+        let original = "Fix bug +oldname\nWrite docs +other\n";
+        let mut list = TaskList::parse("todo.txt", original);
+        let operations = [
+            EditOperation::RenameProject { from: String::from("oldname"), to: String::from("newname") },
+            EditOperation::AddContext(String::from("office")),
+        ];
+        let diff = bulk_edit(&mut list, |task : &Task| task.projects().contains(&String::from("oldname")), &operations, true);
+
+        assert_eq!(diff.changes, vec![LineChange { line: 1, old: String::from("Fix bug +oldname"), new: String::from("Fix bug +newname @office") }]);
+        assert_eq!(list.to_string(), original);
+        assert!(list.history().is_empty());
+    }
+}
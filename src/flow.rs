@@ -0,0 +1,143 @@
+use chrono::prelude::*;
+use std::fmt::Write;
+use task::Task;
+
+/// The state of a day in a burndown or cumulative flow chart.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DayCounts {
+    pub date: Date<Utc>,
+    /// Tasks created on or before the day and not completed by the end of it.
+    pub open: usize,
+    pub created: usize,
+    pub completed: usize,
+    /// Tasks completed on or before the day.
+    pub done: usize,
+}
+
+/// Per-day counts from `from` to `to`, both included, e.g. over the tasks of todo.txt and done.txt.
+///
+/// Open tasks without a creation date and completed tasks without a completion date
+/// can't be placed in time and are left out. Completed tasks with only a completion
+/// date count as created on that day. With a project only its tasks are counted.
+pub fn daily_flow<'a, I>(tasks : I, from : Date<Utc>, to : Date<Utc>, project : Option<&str>) -> Vec<DayCounts>
+    where I: IntoIterator<Item = &'a Task> {
+    let spans = tasks.into_iter()
+        .filter(|task| project.is_none_or(|project| task.projects().iter().any(|name| name == project)))
+        .filter_map(|task| match (task.created_at(), task.completed, task.completed_at()) {
+            (Some(created_at), false, _) => Some((created_at, None)),
+            (Some(created_at), true, Some(completed_at)) => Some((created_at, Some(completed_at))),
+            (None, true, Some(completed_at)) => Some((completed_at, Some(completed_at))),
+            _ => None,
+        })
+        .collect::<Vec<(Date<Utc>, Option<Date<Utc>>)>>();
+
+    let mut series = Vec::new();
+    let mut date = from;
+    while date <= to {
+        let mut counts = DayCounts { date, open: 0, created: 0, completed: 0, done: 0 };
+        for &(created_at, completed_at) in &spans {
+            if created_at == date {
+                counts.created += 1;
+            }
+            match completed_at {
+                Some(completed_at) if completed_at <= date => {
+                    counts.done += 1;
+                    if completed_at == date {
+                        counts.completed += 1;
+                    }
+                },
+                _ if created_at <= date => counts.open += 1,
+                _ => {},
+            }
+        }
+        series.push(counts);
+        date = date.succ();
+    }
+    series
+}
+
+/// One line per day with a bar of `#` for open and `.` for done tasks, scaled to `width` characters.
+pub fn render_ascii(series : &[DayCounts], width : usize) -> String {
+    let maximum = series.iter().map(|counts| counts.open + counts.done).max().unwrap_or(0).max(1);
+    let scale = |count : usize| (count * width + maximum / 2) / maximum;
+    let mut output = String::new();
+    for counts in series {
+        let open = scale(counts.open);
+        let done = scale(counts.open + counts.done) - open;
+        let bar = format!("{}{}", "#".repeat(open), ".".repeat(done));
+        writeln!(output, "{} {:width$} {} open, {} done", counts.date.format("%F"), bar, counts.open, counts.done, width = width)
+            .expect("Writing to a String can't fail.");
+    }
+    output
+}
+
+/// A cumulative flow chart with done tasks stacked below open tasks, the top edge
+/// of the open area is the burndown.
+pub fn render_svg(series : &[DayCounts], width : u32, height : u32) -> String {
+    let maximum = series.iter().map(|counts| counts.open + counts.done).max().unwrap_or(0).max(1) as f64;
+    let step = if series.len() > 1 { f64::from(width) / (series.len() - 1) as f64 } else { 0.0 };
+    let y = |count : usize| f64::from(height) - count as f64 / maximum * f64::from(height);
+    let area = |lower : &dyn Fn(&DayCounts) -> usize, upper : &dyn Fn(&DayCounts) -> usize| {
+        let top = series.iter().enumerate().map(|(index, counts)| format!("{:.1},{:.1}", index as f64 * step, y(upper(counts))));
+        let bottom = series.iter().enumerate().rev().map(|(index, counts)| format!("{:.1},{:.1}", index as f64 * step, y(lower(counts))));
+        top.chain(bottom).collect::<Vec<String>>().join(" ")
+    };
+
+    let mut output = String::new();
+    writeln!(output, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="{1}" viewBox="0 0 {0} {1}">"#, width, height)
+        .expect("Writing to a String can't fail.");
+    if !series.is_empty() {
+        writeln!(output, r##"  <polygon class="done" fill="#4caf50" points="{}"/>"##, area(&|_| 0, &|counts| counts.done))
+            .expect("Writing to a String can't fail.");
+        writeln!(output, r##"  <polygon class="open" fill="#f44336" points="{}"/>"##, area(&|counts| counts.done, &|counts| counts.done + counts.open))
+            .expect("Writing to a String can't fail.");
+    }
+    output.push_str("</svg>\n");
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use flow::*;
+    use task_list::TaskList;
+
+    fn fixture() -> Vec<DayCounts> {
+        let todo = TaskList::parse("todo.txt", "2018-11-20 Write docs +rustodo\n2018-11-22 Call mom\nNo date +rustodo\n");
+        let done = TaskList::parse("done.txt", "x 2018-11-21 2018-11-19 Fix bug +rustodo\nx 2018-11-22 Undated completion +rustodo\n");
+        daily_flow(todo.tasks().chain(done.tasks()).map(|(_, task)| task), Utc.ymd(2018, 11, 19), Utc.ymd(2018, 11, 22), None)
+    }
+
+    #[test]
+    fn counts_open_created_and_completed_per_day() {
+        let series = fixture()
+            .iter()
+            .map(|counts| (counts.date.day(), counts.open, counts.created, counts.completed, counts.done))
+            .collect::<Vec<(u32, usize, usize, usize, usize)>>();
+
+        assert_eq!(series, vec![(19, 1, 1, 0, 0), (20, 2, 1, 0, 0), (21, 1, 0, 1, 1), (22, 2, 2, 1, 2)]);
+    }
+
+    #[test]
+    fn series_can_be_filtered_by_project() {
+        let list = TaskList::parse("todo.txt", "2018-11-20 Write docs +rustodo\n2018-11-20 Call mom\n");
+        let series = daily_flow(list.tasks().map(|(_, task)| task), Utc.ymd(2018, 11, 20), Utc.ymd(2018, 11, 20), Some("rustodo"));
+
+        assert_eq!(series[0].open, 1);
+    }
+
+    #[test]
+    fn renders_ascii_bars() {
+        assert_eq!(render_ascii(&fixture()[2..], 6),
+            "2018-11-21 ##.    1 open, 1 done\n2018-11-22 ###... 2 open, 2 done\n");
+    }
+
+    #[test]
+    fn renders_stacked_svg_areas() {
+        let svg = render_svg(&fixture()[2..], 100, 30);
+
+        assert!(svg.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="30""#));
+        assert!(svg.contains(r##"class="done" fill="#4caf50" points="0.0,22.5 100.0,15.0 100.0,30.0 0.0,30.0""##));
+        assert!(svg.contains(r##"class="open" fill="#f44336" points="0.0,15.0 100.0,0.0 100.0,15.0 0.0,22.5""##));
+        assert!(svg.ends_with("</svg>\n"));
+    }
+}
@@ -0,0 +1,236 @@
+use chrono::prelude::*;
+use std::cmp::Ordering;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Error;
+use std::fs;
+use std::io;
+use std::path::Path;
+use toml;
+use description_component::DescriptionComponent;
+use edit::Diff;
+use edit::LineChange;
+use line::Line;
+use sort::SortSpec;
+use sort::UnknownSortKey;
+use task::Task;
+use task_list::TaskList;
+
+/// The canonical form of tasks, so different clients produce the same lines.
+///
+/// Tasks are written with their leading fields in the order of the spec and single
+/// spaces between words. Relative dates are resolved, options can be moved to the
+/// end in a fixed order and the tasks can be sorted. The rules are configured in
+/// the `format` table of a TOML config:
+///
+/// ```toml
+/// [format]
+/// key_order = ["due", "t"]
+/// normalize_dates = true
+/// sort = "pri,due,text"
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Format {
+    /// With an order, options are moved to the end of the description: the listed keys
+    /// first, the others alphabetically. Without one they stay where they are.
+    pub key_order: Option<Vec<String>>,
+    /// Whether relative dates like `due:fri` are written as ISO dates.
+    pub normalize_dates: bool,
+    /// Sorts the tasks within the blocks between comments and blank lines, so sections
+    /// stay intact. Ties are broken by the text, the result doesn't depend on the input order.
+    pub sort: Option<SortSpec>,
+}
+
+impl Default for Format {
+    fn default() -> Format {
+        Format {
+            key_order: None,
+            normalize_dates: true,
+            sort: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum FormatError {
+    Io(io::Error),
+    Toml(toml::de::Error),
+    Sort(UnknownSortKey),
+}
+
+impl Display for FormatError {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
+        match self {
+            FormatError::Io(error) => write!(formatter, "Couldn't read the format: {}", error),
+            FormatError::Toml(error) => write!(formatter, "Couldn't parse the format: {}", error),
+            FormatError::Sort(error) => write!(formatter, "Invalid sort in the format: {}", error),
+        }
+    }
+}
+
+impl From<io::Error> for FormatError {
+    fn from(error : io::Error) -> FormatError {
+        FormatError::Io(error)
+    }
+}
+
+impl From<toml::de::Error> for FormatError {
+    fn from(error : toml::de::Error) -> FormatError {
+        FormatError::Toml(error)
+    }
+}
+
+impl From<UnknownSortKey> for FormatError {
+    fn from(error : UnknownSortKey) -> FormatError {
+        FormatError::Sort(error)
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct FormatConfig {
+    #[serde(default)]
+    format: FormatTable,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct FormatTable {
+    key_order: Option<Vec<String>>,
+    normalize_dates: Option<bool>,
+    sort: Option<String>,
+}
+
+impl Format {
+    /// The default format changed by the `format` table of a TOML config. Other tables are ignored.
+    pub fn parse_config(input : &str) -> Result<Format, FormatError> {
+        let config : FormatConfig = toml::from_str(input)?;
+        let table = config.format;
+        Ok(Format {
+            key_order: table.key_order,
+            normalize_dates: table.normalize_dates.unwrap_or(true),
+            sort: match table.sort {
+                Some(sort) => Some(SortSpec::parse(&sort)?),
+                None => None,
+            },
+        })
+    }
+
+    pub fn load_config<P: AsRef<Path>>(path : P) -> Result<Format, FormatError> {
+        Format::parse_config(&fs::read_to_string(path)?)
+    }
+
+    /// A task in canonical form.
+    pub fn task(&self, task : &Task, today : Date<Utc>) -> Task {
+        let mut task = task.clone();
+        if self.normalize_dates {
+            task.normalize_dates(today);
+        }
+
+        let components = task.description_components().clone();
+        let (mut options, text) = match self.key_order {
+            Some(_) => components.into_iter().partition(|component| matches!(component, DescriptionComponent::KeyValue(..))),
+            None => (Vec::new(), components),
+        };
+        let rank = |component : &DescriptionComponent| match component {
+            DescriptionComponent::KeyValue(key, _) => {
+                let order = self.key_order.as_deref().unwrap_or(&[]);
+                (order.iter().position(|listed| listed == key).unwrap_or(order.len()), key.clone())
+            },
+            _ => (0, String::new()),
+        };
+        //stable, so repeated keys keep their order
+        options.sort_by_key(|option| rank(option));
+
+        let words = text.iter()
+            .map(|component| component.to_string())
+            .collect::<String>();
+        let words = words.split_whitespace()
+            .map(String::from)
+            .chain(options.iter().map(|option| option.to_string()))
+            .collect::<Vec<String>>();
+        task.set_description(&words.join(" "));
+        task
+    }
+
+    /// The lines in canonical form. Comments and blank lines stay where they are.
+    pub fn lines(&self, lines : &[Line], today : Date<Utc>) -> Vec<Line> {
+        let mut formatted = lines.iter()
+            .map(|line| match line {
+                Line::Task(task) => Line::Task(self.task(task, today)),
+                line => line.clone(),
+            })
+            .collect::<Vec<Line>>();
+        if let Some(ref sort) = self.sort {
+            for block in formatted.split_mut(|line| line.task().is_none()) {
+                block.sort_by(|left, right| match (left, right) {
+                    (Line::Task(left), Line::Task(right)) => sort.compare(left, right)
+                        .then_with(|| left.to_string().cmp(&right.to_string())),
+                    _ => Ordering::Equal,
+                });
+            }
+        }
+        formatted
+    }
+
+    /// Formats a list and returns the changed lines. Sorting moves tasks only between
+    /// task lines, so every change is a replaced task. With `dry_run` the list stays
+    /// unchanged, like a check in CI.
+    pub fn format(&self, list : &mut TaskList, today : Date<Utc>, dry_run : bool) -> Diff {
+        let formatted = self.lines(list.lines(), today);
+        let changes = list.lines().iter()
+            .zip(formatted)
+            .enumerate()
+            .filter_map(|(index, (old, new))| match new {
+                Line::Task(task) if old.to_string() != task.to_string() => Some((index + 1, old.to_string(), task)),
+                _ => None,
+            })
+            .collect::<Vec<(usize, String, Task)>>();
+
+        let mut diff = Diff::default();
+        for (line, old, task) in changes {
+            let new = task.to_string();
+            if !dry_run {
+                list.replace(line, task);
+            }
+            diff.changes.push(LineChange { line, old, new });
+        }
+        diff
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use format::*;
+
+    #[test]
+    fn collapses_spaces_and_orders_options() {
+        let format = Format { key_order: Some(vec![String::from("due")]), ..Format::default() };
+        let task = Task::new("(A)  2018-11-20 Pay  owner:max rent due:fri   +home  t:2018-11-22 ");
+
+        assert_eq!(Format::default().task(&task, Utc.ymd(2018, 11, 20)).to_string(), "(A) 2018-11-20 Pay owner:max rent due:2018-11-23 +home t:2018-11-22");
+        assert_eq!(format.task(&task, Utc.ymd(2018, 11, 20)).to_string(), "(A) 2018-11-20 Pay rent +home due:2018-11-23 owner:max t:2018-11-22");
+    }
+
+    #[test]
+    fn sorts_within_blocks_deterministically() {
+        let format = Format::parse_config("[format]\nsort = \"pri\"\nnormalize_dates = false\n").expect("Must parse.");
+        let lines = TaskList::parse("todo.txt", "Write docs\n(A) Fix bug\nCall mom\n# Later\n(B) Plan\nBuy milk\n").lines().to_vec();
+        let formatted = format.lines(&lines, Utc.ymd(2018, 11, 24)).iter().map(Line::to_string).collect::<Vec<String>>();
+
+        assert_eq!(formatted, vec!["(A) Fix bug", "Call mom", "Write docs", "# Later", "(B) Plan", "Buy milk"]);
+        assert!(Format::parse_config("[format]\nsort = \"size\"").is_err());
+    }
+
+    #[test]
+    fn format_replaces_changed_lines_unless_dry_run() {
+        let mut list = TaskList::parse("todo.txt", "Pay  rent\n\nCall mom\n");
+
+        let diff = Format::default().format(&mut list, Utc.ymd(2018, 11, 24), true);
+        assert_eq!(diff.to_string(), "-1 Pay  rent\n+1 Pay rent\n");
+        assert_eq!(list.to_string(), "Pay  rent\n\nCall mom\n");
+
+        Format::default().format(&mut list, Utc.ymd(2018, 11, 24), false);
+        assert_eq!(list.to_string(), "Pay rent\n\nCall mom\n");
+        assert!(Format::default().format(&mut list, Utc.ymd(2018, 11, 24), true).is_empty());
+    }
+}
@@ -0,0 +1,164 @@
+use chrono::prelude::*;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Error;
+use parsers::*;
+use task::Task;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GroupKey {
+    Project,
+    Context,
+    Option(String),
+    /// The ISO week of the due date, e.g. `2018-W47`.
+    DueWeek,
+}
+
+/// The name of a group. Tasks without a value for the key end up in `None`, which sorts last.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum GroupName {
+    Value(String),
+    None,
+}
+
+impl Display for GroupName {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
+        match self {
+            GroupName::Value(value) => write!(formatter, "{}", value),
+            GroupName::None => write!(formatter, "none"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnknownGroupKey(pub String);
+
+impl Display for UnknownGroupKey {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
+        write!(formatter, "Unknown group key '{}', options are grouped with 'key:NAME'.", self.0)
+    }
+}
+
+impl GroupKey {
+    /// Parses `project`, `context`, `due-week` or `key:NAME` for the option `NAME`.
+    pub fn parse(input : &str) -> Result<GroupKey, UnknownGroupKey> {
+        match input.trim() {
+            "project" => Ok(GroupKey::Project),
+            "context" => Ok(GroupKey::Context),
+            "due-week" => Ok(GroupKey::DueWeek),
+            key => match key.strip_prefix("key:") {
+                Some(option) if !option.is_empty() => Ok(GroupKey::Option(String::from(option))),
+                _ => Err(UnknownGroupKey(String::from(key))),
+            },
+        }
+    }
+}
+
+pub type Groups<'a, T> = BTreeMap<GroupName, Vec<(T, &'a Task)>>;
+
+/// Groups tasks together with a handle like their line number or `Location`, so
+/// `group_by(list.tasks(), &GroupKey::Project)` works as well as `group_by(workspace.tasks(), ...)`.
+///
+/// Groups are ordered by name and keep the order of their tasks. A task with
+/// several values for the key, e.g. several projects, is part of every matching group.
+/// Grouping the tasks of a group again gives nested groups.
+pub fn group_by<'a, T, I>(tasks : I, key : &GroupKey) -> Groups<'a, T>
+    where T: Clone, I: IntoIterator<Item = (T, &'a Task)> {
+    let mut groups = Groups::new();
+    for (handle, task) in tasks {
+        let mut names = group_names(task, key);
+        names.dedup();
+        if names.is_empty() {
+            names.push(GroupName::None);
+        }
+        for name in names {
+            groups.entry(name).or_default().push((handle.clone(), task));
+        }
+    }
+    groups
+}
+
+/// The number of tasks in each group.
+pub fn group_counts<T>(groups : &Groups<T>) -> BTreeMap<GroupName, usize> {
+    groups.iter().map(|(name, tasks)| (name.clone(), tasks.len())).collect()
+}
+
+fn group_names(task : &Task, key : &GroupKey) -> Vec<GroupName> {
+    let mut values = match key {
+        GroupKey::Project => task.projects(),
+        GroupKey::Context => task.contexts(),
+        GroupKey::Option(key) => task.options().get(key).cloned().into_iter().collect(),
+        GroupKey::DueWeek => task.options().get("due")
+            .and_then(|due| DateParser::parse(due))
+            .filter(|date_result| date_result.remaining.is_empty())
+            .map(|date_result| iso_week_name(date_result.value))
+            .into_iter()
+            .collect(),
+    };
+    values.sort();
+    values.into_iter().map(GroupName::Value).collect()
+}
+
+/// The ISO week of a date like `2018-W47`.
+pub(crate) fn iso_week_name(date : Date<Utc>) -> String {
+    let week = date.iso_week();
+    format!("{}-W{:02}", week.year(), week.week())
+}
+
+#[cfg(test)]
+mod tests {
+    use group::*;
+    use task_list::TaskList;
+
+    fn names<T>(groups : &Groups<T>) -> Vec<String> {
+        groups.keys().map(|name| name.to_string()).collect()
+    }
+
+    fn lines(tasks : &[(usize, &Task)]) -> Vec<usize> {
+        tasks.iter().map(|&(line, _)| line).collect()
+    }
+
+    #[test]
+    fn tasks_with_several_projects_are_in_every_group() {
+        let list = TaskList::parse("todo.txt", "Write docs +rustodo +docs\nFix bug +rustodo\nCall mom\nDuplicate +docs +docs\n");
+        let groups = group_by(list.tasks(), &GroupKey::Project);
+
+        assert_eq!(names(&groups), vec!["docs", "rustodo", "none"]);
+        assert_eq!(lines(&groups[&GroupName::Value(String::from("docs"))]), vec![1, 4]);
+        assert_eq!(lines(&groups[&GroupName::Value(String::from("rustodo"))]), vec![1, 2]);
+        assert_eq!(lines(&groups[&GroupName::None]), vec![3]);
+        assert_eq!(group_counts(&groups)[&GroupName::Value(String::from("rustodo"))], 2);
+    }
+
+    #[test]
+    fn groups_can_be_nested() {
+        let list = TaskList::parse("todo.txt", "A +work @office\nB +work @phone\nC +home @phone\n");
+        let projects = group_by(list.tasks(), &GroupKey::Project);
+        let work_contexts = group_by(projects[&GroupName::Value(String::from("work"))].clone(), &GroupKey::Context);
+
+        assert_eq!(names(&work_contexts), vec!["office", "phone"]);
+        assert_eq!(lines(&work_contexts[&GroupName::Value(String::from("phone"))]), vec![2]);
+    }
+
+    #[test]
+    fn groups_by_option_and_due_week() {
+        let list = TaskList::parse("todo.txt", "A owner:max due:2018-11-24\nB owner:thomas due:2018-11-19\nC due:2018-11-26\nD due:soon\n");
+
+        let owners = group_by(list.tasks(), &GroupKey::Option(String::from("owner")));
+        assert_eq!(names(&owners), vec!["max", "thomas", "none"]);
+
+        let weeks = group_by(list.tasks(), &GroupKey::DueWeek);
+        assert_eq!(names(&weeks), vec!["2018-W47", "2018-W48", "none"]);
+        assert_eq!(lines(&weeks[&GroupName::Value(String::from("2018-W47"))]), vec![1, 2]);
+        assert_eq!(lines(&weeks[&GroupName::None]), vec![4]);
+    }
+
+    #[test]
+    fn options_have_to_be_named_explicitly() {
+        assert_eq!(GroupKey::parse(" key:owner"), Ok(GroupKey::Option(String::from("owner"))));
+        assert_eq!(GroupKey::parse("due-week"), Ok(GroupKey::DueWeek));
+        assert_eq!(GroupKey::parse("projects"), Err(UnknownGroupKey(String::from("projects"))));
+        assert_eq!(GroupKey::parse("key:"), Err(UnknownGroupKey(String::from("key:"))));
+    }
+}
@@ -0,0 +1,178 @@
+use chrono::prelude::*;
+use std::collections::HashSet;
+use description_component::DescriptionComponent;
+use task::Task;
+use task_list::TaskList;
+
+/// Option keys whose value identifies a task, in order of preference.
+pub const IDENTITY_KEYS : [&str; 2] = ["id", "uuid"];
+
+/// Minimum similarity for a task to count as the same task after an edit.
+pub const SIMILARITY_THRESHOLD : f64 = 0.8;
+
+/// Recognizes a task again after lines were added, removed or slightly edited.
+///
+/// Tasks with an `id:` or `uuid:` option are identified by its value. All other
+/// tasks are matched fuzzily on their text, projects and creation date.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TaskIdentity {
+    Key(String),
+    Fingerprint {
+        text: String,
+        projects: Vec<String>,
+        created_at: Option<Date<Utc>>,
+    },
+}
+
+impl TaskIdentity {
+    pub fn of(task : &Task) -> TaskIdentity {
+        match identity_key(task) {
+            Some(key) => TaskIdentity::Key(key),
+            None => TaskIdentity::Fingerprint {
+                text: normalized_text(task),
+                projects: task.projects(),
+                created_at: task.created_at(),
+            }
+        }
+    }
+
+    /// How similar a task is to this identity, from 0.0 (different) to 1.0 (identical).
+    pub fn similarity(&self, task : &Task) -> f64 {
+        match self {
+            TaskIdentity::Key(key) => match identity_key(task) {
+                Some(ref other_key) if other_key == key => 1.0,
+                _ => 0.0,
+            },
+            TaskIdentity::Fingerprint { text, projects, created_at } => {
+                if let (Some(created_at), Some(other_created_at)) = (created_at, task.created_at()) {
+                    if *created_at != other_created_at {
+                        return 0.0;
+                    }
+                }
+                0.7 * text_similarity(text, &normalized_text(task)) + 0.3 * set_similarity(projects, &task.projects())
+            }
+        }
+    }
+
+    pub fn matches(&self, task : &Task) -> bool {
+        self.similarity(task) >= SIMILARITY_THRESHOLD
+    }
+
+    /// The line of the best matching task in the list, if any task is similar enough.
+    pub fn locate(&self, list : &TaskList) -> Option<usize> {
+        let mut best : Option<(usize, f64)> = None;
+        for (line, task) in list.tasks() {
+            let similarity = self.similarity(task);
+            let is_better = match best {
+                Some((_, best_similarity)) => similarity > best_similarity,
+                None => similarity >= SIMILARITY_THRESHOLD,
+            };
+            if is_better {
+                best = Some((line, similarity));
+            }
+        }
+        best.map(|(line, _)| line)
+    }
+}
+
+fn identity_key(task : &Task) -> Option<String> {
+    let options = task.options();
+    IDENTITY_KEYS.iter().filter_map(|key| options.get(*key).cloned()).next()
+}
+
+/// The text components of a task with whitespace collapsed, so moving a tag
+/// or adding a space doesn't change the fingerprint.
+fn normalized_text(task : &Task) -> String {
+    task.description_components().iter()
+        .filter_map(|component| match component {
+            DescriptionComponent::Text(text) => Some(text.as_str()),
+            _ => None,
+        })
+        .flat_map(|text| text.split_whitespace())
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+fn text_similarity(left : &str, right : &str) -> f64 {
+    let left = left.chars().collect::<Vec<char>>();
+    let right = right.chars().collect::<Vec<char>>();
+    let length = left.len().max(right.len());
+    if length == 0 {
+        return 1.0;
+    }
+    1.0 - levenshtein(&left, &right) as f64 / length as f64
+}
+
+fn set_similarity(left : &[String], right : &[String]) -> f64 {
+    let left = left.iter().collect::<HashSet<&String>>();
+    let right = right.iter().collect::<HashSet<&String>>();
+    let union = left.union(&right).count();
+    if union == 0 {
+        return 1.0;
+    }
+    left.intersection(&right).count() as f64 / union as f64
+}
+
+fn levenshtein(left : &[char], right : &[char]) -> usize {
+    let mut previous_row = (0..=right.len()).collect::<Vec<usize>>();
+    for (left_index, left_char) in left.iter().enumerate() {
+        let mut row = vec![left_index + 1];
+        for (right_index, right_char) in right.iter().enumerate() {
+            let substitution = previous_row[right_index] + if left_char == right_char { 0 } else { 1 };
+            let deletion = previous_row[right_index + 1] + 1;
+            let insertion = row[right_index] + 1;
+            row.push(substitution.min(deletion).min(insertion));
+        }
+        previous_row = row;
+    }
+    previous_row[right.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use identity::*;
+
+    #[test]
+    fn tasks_with_id_are_identified_by_key() {
+        let identity = TaskIdentity::of(&Task::new("Write report id:42"));
+        assert_eq!(identity, TaskIdentity::Key(String::from("42")));
+
+        assert!(identity.matches(&Task::new("(A) Write the whole report +work id:42")));
+        assert!(!identity.matches(&Task::new("Write report id:43")));
+        assert!(!identity.matches(&Task::new("Write report")));
+    }
+
+    #[test]
+    fn uuid_is_used_when_there_is_no_id() {
+        let identity = TaskIdentity::of(&Task::new("Write report uuid:a4e1"));
+        assert_eq!(identity, TaskIdentity::Key(String::from("a4e1")));
+    }
+
+    #[test]
+    fn fingerprint_survives_small_edits() {
+        let identity = TaskIdentity::of(&Task::new("2018-11-24 Write the quarterly report +work"));
+
+        assert!(identity.matches(&Task::new("(A) 2018-11-24 Write the quarterly  reports +work @office")));
+        assert!(!identity.matches(&Task::new("2018-11-24 Call mom +work")));
+        assert!(!identity.matches(&Task::new("2018-11-25 Write the quarterly report +work")));
+    }
+
+    #[test]
+    fn locate_follows_tasks_across_reorderings() {
+        let identity = TaskIdentity::of(&Task::new("Water the plants @home"));
+        let list = TaskList::parse("todo.txt", "Call mom\nWrite report\nWater the plant @home\nBuy milk\n");
+
+        assert_eq!(identity.locate(&list), Some(3));
+        assert_eq!(TaskIdentity::of(&Task::new("Something else entirely")).locate(&list), None);
+    }
+
+    #[test]
+    fn levenshtein_counts_edits() {
+        let chars = |text : &str| text.chars().collect::<Vec<char>>();
+
+        assert_eq!(levenshtein(&chars("kitten"), &chars("sitting")), 3);
+        assert_eq!(levenshtein(&chars(""), &chars("abc")), 3);
+        assert_eq!(levenshtein(&chars("same"), &chars("same")), 0);
+    }
+}
@@ -0,0 +1,235 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::hash::Hash;
+use task::Task;
+
+/// Something a task can be looked up by.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum IndexTerm {
+    Project(String),
+    Context(String),
+    /// Tasks with an option, whatever its value is.
+    OptionKey(String),
+    Option(String, String),
+}
+
+/// An inverted index from projects, contexts and options to task handles like line numbers.
+///
+/// Lookups don't have to walk the description components of every task. Use
+/// `insert` and `remove` to keep the index up to date while tasks are edited.
+#[derive(Debug, Clone)]
+pub struct TaskIndex<T: Ord + Clone + Hash> {
+    postings: HashMap<IndexTerm, BTreeSet<T>>,
+    //ordered, so the handles after a line can be split off
+    terms: BTreeMap<T, Vec<IndexTerm>>,
+}
+
+impl<T: Ord + Clone + Hash> Default for TaskIndex<T> {
+    fn default() -> TaskIndex<T> {
+        TaskIndex::new()
+    }
+}
+
+impl<T: Ord + Clone + Hash> TaskIndex<T> {
+    pub fn new() -> TaskIndex<T> {
+        TaskIndex {
+            postings: HashMap::new(),
+            terms: BTreeMap::new(),
+        }
+    }
+
+    pub fn build<'a, I: IntoIterator<Item = (T, &'a Task)>>(tasks : I) -> TaskIndex<T> {
+        let mut index = TaskIndex::new();
+        for (handle, task) in tasks {
+            index.insert(handle, task);
+        }
+        index
+    }
+
+    /// Indexes a task, replacing whatever was indexed for the handle before.
+    pub fn insert(&mut self, handle : T, task : &Task) {
+        self.remove(&handle);
+        let terms = index_terms(task);
+        for term in &terms {
+            self.postings.entry(term.clone()).or_default().insert(handle.clone());
+        }
+        self.terms.insert(handle, terms);
+    }
+
+    pub fn remove(&mut self, handle : &T) {
+        for term in self.terms.remove(handle).unwrap_or_default() {
+            let is_empty = match self.postings.get_mut(&term) {
+                Some(handles) => {
+                    handles.remove(handle);
+                    handles.is_empty()
+                },
+                None => false,
+            };
+            if is_empty {
+                self.postings.remove(&term);
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.terms.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    pub fn get(&self, term : &IndexTerm) -> Option<&BTreeSet<T>> {
+        self.postings.get(term)
+    }
+
+    /// The tasks that have all of the terms.
+    pub fn all(&self, terms : &[IndexTerm]) -> BTreeSet<T> {
+        let mut postings = Vec::with_capacity(terms.len());
+        for term in terms {
+            match self.postings.get(term) {
+                Some(handles) => postings.push(handles),
+                None => return BTreeSet::new(),
+            }
+        }
+        postings.sort_by_key(|handles| handles.len());
+        let mut postings = postings.into_iter();
+        let smallest = match postings.next() {
+            Some(handles) => handles.clone(),
+            None => return BTreeSet::new(),
+        };
+        postings.fold(smallest, |result, handles| result.intersection(handles).cloned().collect())
+    }
+
+    /// The tasks that have at least one of the terms.
+    pub fn any(&self, terms : &[IndexTerm]) -> BTreeSet<T> {
+        terms.iter()
+            .filter_map(|term| self.postings.get(term))
+            .flat_map(|handles| handles.iter().cloned())
+            .collect()
+    }
+
+    /// All indexed terms in order, e.g. to list every project.
+    pub fn terms(&self) -> BTreeSet<&IndexTerm> {
+        self.postings.keys().collect()
+    }
+}
+
+impl TaskIndex<usize> {
+    /// Moves the line numbers after a line that was inserted or removed. Removed
+    /// lines have to be removed from the index first. Only the tasks at or after
+    /// the line are touched, so appending is cheap.
+    pub fn shift_lines(&mut self, line : usize, inserted : bool) {
+        if self.terms.keys().next_back().is_none_or(|&last| last < line) {
+            return;
+        }
+        let shift = |handle : usize| if inserted { handle + 1 } else { handle - 1 };
+        let moved = self.terms.split_off(&line);
+        let moved_terms = moved.values().flatten().collect::<HashSet<&IndexTerm>>();
+        for term in moved_terms {
+            if let Some(handles) = self.postings.get_mut(term) {
+                let tail = handles.split_off(&line);
+                handles.extend(tail.into_iter().map(shift));
+            }
+        }
+        self.terms.extend(moved.into_iter().map(|(handle, terms)| (shift(handle), terms)));
+    }
+}
+
+fn index_terms(task : &Task) -> Vec<IndexTerm> {
+    let mut terms = task.projects().into_iter().map(IndexTerm::Project)
+        .chain(task.contexts().into_iter().map(IndexTerm::Context))
+        .chain(task.options().into_iter().flat_map(|(key, value)| [IndexTerm::OptionKey(key.clone()), IndexTerm::Option(key, value)]))
+        .collect::<Vec<IndexTerm>>();
+    terms.sort();
+    terms.dedup();
+    terms
+}
+
+#[cfg(test)]
+mod tests {
+    use index::*;
+
+    fn project(name : &str) -> IndexTerm {
+        IndexTerm::Project(String::from(name))
+    }
+
+    fn context(name : &str) -> IndexTerm {
+        IndexTerm::Context(String::from(name))
+    }
+
+    fn handles(set : BTreeSet<usize>) -> Vec<usize> {
+        set.into_iter().collect()
+    }
+
+    fn fixture() -> TaskIndex<usize> {
+        let tasks = [
+            Task::new("Write docs +rustodo @office"),
+            Task::new("Fix bug +rustodo @home owner:max"),
+            Task::new("Call mom @phone"),
+            Task::new("Plan release +rustodo +release @office owner:thomas"),
+        ];
+        TaskIndex::build(tasks.iter().enumerate().map(|(index, task)| (index + 1, task)))
+    }
+
+    #[test]
+    fn intersections_and_unions() {
+        let index = fixture();
+
+        assert_eq!(handles(index.all(&[project("rustodo"), context("office")])), vec![1, 4]);
+        assert_eq!(handles(index.all(&[project("rustodo"), context("phone")])), Vec::<usize>::new());
+        assert_eq!(handles(index.all(&[project("rustodo"), project("unknown")])), Vec::<usize>::new());
+        assert_eq!(handles(index.any(&[context("home"), context("phone")])), vec![2, 3]);
+        assert_eq!(handles(index.all(&[IndexTerm::OptionKey(String::from("owner"))])), vec![2, 4]);
+        assert_eq!(handles(index.all(&[IndexTerm::Option(String::from("owner"), String::from("max"))])), vec![2]);
+    }
+
+    #[test]
+    fn insert_replaces_and_remove_forgets() {
+        let mut index = fixture();
+
+        index.insert(3, &Task::new("Call mom +family @phone"));
+        assert_eq!(handles(index.all(&[project("family")])), vec![3]);
+
+        index.insert(1, &Task::new("Write docs"));
+        assert_eq!(handles(index.all(&[project("rustodo")])), vec![2, 4]);
+
+        index.remove(&4);
+        assert!(index.get(&project("release")).is_none());
+        assert_eq!(index.len(), 3);
+    }
+
+    #[test]
+    fn lines_can_be_shifted() {
+        let mut index = fixture();
+
+        index.remove(&2);
+        index.shift_lines(2, false);
+        assert_eq!(handles(index.all(&[project("rustodo")])), vec![1, 3]);
+
+        index.shift_lines(1, true);
+        assert_eq!(handles(index.all(&[project("rustodo")])), vec![2, 4]);
+        assert_eq!(handles(index.all(&[context("phone")])), vec![3]);
+
+        index.shift_lines(5, true);
+        index.shift_lines(3, true);
+        assert_eq!(handles(index.all(&[project("rustodo")])), vec![2, 5]);
+        assert_eq!(handles(index.all(&[context("office")])), vec![2, 5]);
+        assert_eq!(handles(index.all(&[context("phone")])), vec![4]);
+    }
+
+    #[test]
+    fn terms_are_listed_in_order() {
+        let index = fixture();
+        let projects = index.terms().into_iter()
+            .filter_map(|term| match term {
+                IndexTerm::Project(project) => Some(project.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<&str>>();
+
+        assert_eq!(projects, vec!["release", "rustodo"]);
+    }
+}
@@ -0,0 +1,282 @@
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Error;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+use task_list::write_atomically;
+
+const JOURNAL_HEADER : &str = "rustodo-journal 1";
+
+/// A reversible change to a task list. Tasks are stored as their todo.txt lines.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operation {
+    Add { line: usize, task: String },
+    Remove { line: usize, task: String },
+    Replace { line: usize, old: String, new: String },
+    Complete { line: usize, old: String, new: String },
+    Reprioritize { line: usize, old: String, new: String },
+}
+
+impl Operation {
+    pub fn line(&self) -> usize {
+        match self {
+            Operation::Add { line, .. }
+                | Operation::Remove { line, .. }
+                | Operation::Replace { line, .. }
+                | Operation::Complete { line, .. }
+                | Operation::Reprioritize { line, .. } => *line
+        }
+    }
+
+    /// The operation that reverts this one.
+    pub fn inverse(&self) -> Operation {
+        match self.clone() {
+            Operation::Add { line, task } => Operation::Remove { line, task },
+            Operation::Remove { line, task } => Operation::Add { line, task },
+            Operation::Replace { line, old, new } => Operation::Replace { line, old: new, new: old },
+            Operation::Complete { line, old, new } => Operation::Complete { line, old: new, new: old },
+            Operation::Reprioritize { line, old, new } => Operation::Reprioritize { line, old: new, new: old },
+        }
+    }
+
+    fn parse(input : &str) -> Option<Operation> {
+        let fields = input.split('\t').map(unescape).collect::<Vec<String>>();
+        //lines are counted from 1, a journal edited by hand may say otherwise
+        let line = fields.get(1)?.parse::<usize>().ok().filter(|&line| line > 0)?;
+        match (fields[0].as_str(), fields.len()) {
+            ("add", 3) => Some(Operation::Add { line, task: fields[2].clone() }),
+            ("remove", 3) => Some(Operation::Remove { line, task: fields[2].clone() }),
+            ("replace", 4) => Some(Operation::Replace { line, old: fields[2].clone(), new: fields[3].clone() }),
+            ("complete", 4) => Some(Operation::Complete { line, old: fields[2].clone(), new: fields[3].clone() }),
+            ("reprioritize", 4) => Some(Operation::Reprioritize { line, old: fields[2].clone(), new: fields[3].clone() }),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Operation {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
+        match self {
+            Operation::Add { line, task } => write!(formatter, "add\t{}\t{}", line, escape(task)),
+            Operation::Remove { line, task } => write!(formatter, "remove\t{}\t{}", line, escape(task)),
+            Operation::Replace { line, old, new } => write!(formatter, "replace\t{}\t{}\t{}", line, escape(old), escape(new)),
+            Operation::Complete { line, old, new } => write!(formatter, "complete\t{}\t{}\t{}", line, escape(old), escape(new)),
+            Operation::Reprioritize { line, old, new } => write!(formatter, "reprioritize\t{}\t{}\t{}", line, escape(old), escape(new)),
+        }
+    }
+}
+
+/// The undo/redo history of a task list.
+///
+/// Operations before `position` have been applied, the ones after it were undone
+/// and can be redone until a new operation is recorded. A journal with a path is
+/// written to that file whenever its list is saved.
+#[derive(Debug, Clone, Default)]
+pub struct Journal {
+    path: Option<PathBuf>,
+    operations: Vec<Operation>,
+    position: usize,
+}
+
+impl Journal {
+    pub fn new() -> Journal {
+        Journal {
+            path: None,
+            operations: Vec::new(),
+            position: 0,
+        }
+    }
+
+    /// The journal file belonging to a todo file, e.g. `todo.txt.journal` for `todo.txt`.
+    pub fn sidecar_path(list_path : &Path) -> PathBuf {
+        let mut file_name = list_path.file_name().map(|name| name.to_os_string()).unwrap_or_default();
+        file_name.push(".journal");
+        list_path.with_file_name(file_name)
+    }
+
+    /// Loads a journal file, a missing file results in an empty journal.
+    pub fn load<P: AsRef<Path>>(path : P) -> io::Result<Journal> {
+        let mut journal = Journal::new();
+        journal.path = Some(path.as_ref().to_path_buf());
+
+        let content = match fs::read_to_string(path.as_ref()) {
+            Ok(content) => content,
+            Err(ref error) if error.kind() == io::ErrorKind::NotFound => return Ok(journal),
+            Err(error) => return Err(error),
+        };
+
+        let invalid = |message : &str| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.as_ref().display(), message));
+        let mut lines = content.lines();
+        if lines.next() != Some(JOURNAL_HEADER) {
+            return Err(invalid("Not a journal file."));
+        }
+        journal.position = lines.next()
+            .and_then(|line| line.trim_start_matches("position ").parse::<usize>().ok())
+            .ok_or_else(|| invalid("Missing position."))?;
+        for line in lines {
+            journal.operations.push(Operation::parse(line).ok_or_else(|| invalid("Invalid operation."))?);
+        }
+        if journal.position > journal.operations.len() {
+            return Err(invalid("Position is out of range."));
+        }
+        Ok(journal)
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        match self.path {
+            Some(ref path) => write_atomically(path, &self.to_string()),
+            None => Ok(()),
+        }
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    pub fn operations(&self) -> &[Operation] {
+        &self.operations
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// The applied operations, oldest first.
+    pub fn history(&self) -> &[Operation] {
+        &self.operations[..self.position]
+    }
+
+    /// Records a new operation, dropping everything that could have been redone.
+    pub fn record(&mut self, operation : Operation) {
+        self.operations.truncate(self.position);
+        self.operations.push(operation);
+        self.position += 1;
+    }
+
+    /// The next operation to undo, already inverted.
+    pub fn undo_operation(&self) -> Option<Operation> {
+        match self.position {
+            0 => None,
+            position => Some(self.operations[position - 1].inverse()),
+        }
+    }
+
+    pub fn redo_operation(&self) -> Option<Operation> {
+        self.operations.get(self.position).cloned()
+    }
+
+    pub fn step_back(&mut self) {
+        self.position = self.position.saturating_sub(1);
+    }
+
+    pub fn step_forward(&mut self) {
+        self.position = (self.position + 1).min(self.operations.len());
+    }
+}
+
+impl Display for Journal {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
+        writeln!(formatter, "{}", JOURNAL_HEADER)?;
+        writeln!(formatter, "position {}", self.position)?;
+        for operation in &self.operations {
+            writeln!(formatter, "{}", operation)?
+        }
+        Ok(())
+    }
+}
+
+fn escape(input : &str) -> String {
+    input.replace('\\', "\\\\").replace('\t', "\\t").replace('\n', "\\n")
+}
+
+fn unescape(input : &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut chars = input.chars();
+    while let Some(character) = chars.next() {
+        if character != '\\' {
+            output.push(character);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => output.push('\t'),
+            Some('n') => output.push('\n'),
+            Some(escaped) => output.push(escaped),
+            None => output.push('\\'),
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use journal::*;
+    use task_list::tests::test_directory;
+
+    #[test]
+    fn operations_round_trip_through_text() {
+        let operations = vec![
+            Operation::Add { line: 3, task: String::from("Buy milk\twith tab \\ and backslash") },
+            Operation::Remove { line: 1, task: String::from("Call mom") },
+            Operation::Replace { line: 2, old: String::from("Old"), new: String::from("New") },
+            Operation::Complete { line: 2, old: String::from("New"), new: String::from("x New") },
+            Operation::Reprioritize { line: 2, old: String::from("New"), new: String::from("(A) New") },
+        ];
+
+        for operation in operations {
+            assert_eq!(Operation::parse(&operation.to_string()), Some(operation));
+        }
+    }
+
+    #[test]
+    fn operations_on_line_zero_are_rejected() {
+        assert_eq!(Operation::parse("add\t0\tBuy milk"), None);
+        assert_eq!(Operation::parse("replace\t0\tOld\tNew"), None);
+        assert_eq!(Operation::parse("add\t1\tBuy milk"), Some(Operation::Add { line: 1, task: String::from("Buy milk") }));
+    }
+
+    #[test]
+    fn inverse_swaps_old_and_new() {
+        let add = Operation::Add { line: 3, task: String::from("Buy milk") };
+        assert_eq!(add.inverse(), Operation::Remove { line: 3, task: String::from("Buy milk") });
+        assert_eq!(add.inverse().inverse(), add);
+
+        let replace = Operation::Replace { line: 2, old: String::from("Old"), new: String::from("New") };
+        assert_eq!(replace.inverse(), Operation::Replace { line: 2, old: String::from("New"), new: String::from("Old") });
+    }
+
+    #[test]
+    fn recording_drops_redoable_operations() {
+        let mut journal = Journal::new();
+        journal.record(Operation::Add { line: 1, task: String::from("First") });
+        journal.record(Operation::Add { line: 2, task: String::from("Second") });
+        journal.step_back();
+
+        assert_eq!(journal.history().len(), 1);
+        assert_eq!(journal.redo_operation(), Some(Operation::Add { line: 2, task: String::from("Second") }));
+
+        journal.record(Operation::Add { line: 2, task: String::from("Third") });
+        assert_eq!(journal.operations().len(), 2);
+        assert_eq!(journal.redo_operation(), None);
+    }
+
+    #[test]
+    fn journal_survives_save_and_load() {
+        let path = test_directory("journal-save").join("todo.txt.journal");
+        let mut journal = Journal::load(&path).expect("Missing journals must load.");
+        journal.record(Operation::Add { line: 1, task: String::from("First") });
+        journal.record(Operation::Remove { line: 1, task: String::from("First") });
+        journal.step_back();
+        journal.save().expect("Must save.");
+
+        let loaded = Journal::load(&path).expect("Must load.");
+        assert_eq!(loaded.operations(), journal.operations());
+        assert_eq!(loaded.position(), 1);
+    }
+
+    #[test]
+    fn sidecar_path_is_next_to_the_list() {
+        assert_eq!(Journal::sidecar_path(Path::new("/home/max/todo.txt")), PathBuf::from("/home/max/todo.txt.journal"));
+    }
+}
@@ -1,10 +1,103 @@
 extern crate chrono;
 extern crate regex;
+extern crate glob;
+extern crate serde;
+#[macro_use] extern crate serde_derive;
+extern crate toml;
+#[cfg(test)] extern crate serde_json;
 #[macro_use] extern crate lazy_static;
 
 mod task;
 mod tokens;
 mod description_component;
 mod parsers;
+mod task_list;
+mod workspace;
+mod identity;
+mod journal;
+mod line;
+mod query;
+mod sort;
+mod group;
+mod search;
+mod index;
+mod edit;
+mod stats;
+mod view;
+mod flow;
+mod config;
+mod render;
+mod lint;
+mod format;
+mod record;
+mod stream;
 
 pub use task::Task;
+pub use description_component::DescriptionComponent;
+pub use task_list::TaskList;
+pub use task_list::SyncReport;
+pub use workspace::Workspace;
+pub use workspace::Location;
+pub use identity::TaskIdentity;
+pub use journal::Journal;
+pub use journal::Operation;
+pub use line::Line;
+pub use query::Query;
+pub use query::QueryError;
+pub use query::Expression;
+pub use query::Operator;
+pub use query::DateField;
+pub use parsers::DateValue;
+pub use parsers::DateUnit;
+pub use parsers::RelativeDateParser;
+pub use sort::SortSpec;
+pub use sort::SortField;
+pub use sort::SortKey;
+pub use sort::UnknownSortKey;
+pub use group::group_by;
+pub use group::group_counts;
+pub use group::GroupKey;
+pub use group::UnknownGroupKey;
+pub use group::GroupName;
+pub use group::Groups;
+pub use search::search;
+pub use search::SearchWeights;
+pub use search::SearchMatch;
+pub use search::SearchResult;
+pub use index::TaskIndex;
+pub use index::IndexTerm;
+pub use edit::bulk_edit;
+pub use edit::EditOperation;
+pub use edit::Diff;
+pub use edit::LineChange;
+pub use edit::Replacement;
+pub use edit::ReplaceScope;
+pub use stats::stats;
+pub use stats::Stats;
+pub use stats::Counts;
+pub use stats::Durations;
+pub use view::View;
+pub use view::ViewResult;
+pub use view::ViewError;
+pub use flow::daily_flow;
+pub use flow::render_ascii;
+pub use flow::render_svg;
+pub use flow::DayCounts;
+pub use config::Config;
+pub use config::ConfigError;
+pub use config::Variables;
+pub use render::parse_color;
+pub use render::Renderer;
+pub use render::Theme;
+pub use lint::Linter;
+pub use lint::LintError;
+pub use lint::Problem;
+pub use lint::Rule;
+pub use lint::RuleConfig;
+pub use lint::Severity;
+pub use format::Format;
+pub use format::FormatError;
+pub use record::TaskRecord;
+pub use stream::filter_stream;
+pub use stream::sort_stream;
+pub use stream::format_stream;
\ No newline at end of file
@@ -0,0 +1,89 @@
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Error;
+use task::Task;
+
+pub const DEFAULT_COMMENT_PREFIX : &str = "#";
+
+/// A line of a todo file. Comments and blank lines are kept as they are, so
+/// headers like `# Sprint 42` and separators survive loading and saving.
+#[derive(Debug, Clone)]
+pub enum Line {
+    Task(Task),
+    Comment(String),
+    /// An empty line or one with whitespace only, which is kept.
+    Blank(String),
+}
+
+impl Line {
+    /// Parses a line, lines starting with the comment prefix become comments.
+    /// Without a prefix every non blank line is a task.
+    pub fn parse(input : &str, comment_prefix : Option<&str>) -> Line {
+        if input.trim().is_empty() {
+            return Line::Blank(String::from(input));
+        }
+        match comment_prefix {
+            Some(prefix) if !prefix.is_empty() && input.starts_with(prefix) => Line::Comment(String::from(input)),
+            _ => Line::Task(Task::new(input)),
+        }
+    }
+
+    pub fn task(&self) -> Option<&Task> {
+        match self {
+            Line::Task(task) => Some(task),
+            _ => None,
+        }
+    }
+
+    /// The text of a comment without its prefix, e.g. `Sprint 42` for `# Sprint 42`.
+    pub fn comment_text(&self, comment_prefix : &str) -> Option<&str> {
+        match self {
+            Line::Comment(comment) => Some(comment.trim_start_matches(comment_prefix).trim()),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Line {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
+        match self {
+            Line::Task(task) => write!(formatter, "{}", task),
+            Line::Comment(comment) => write!(formatter, "{}", comment),
+            Line::Blank(whitespace) => write!(formatter, "{}", whitespace),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use line::*;
+
+    #[test]
+    fn parse_recognizes_comments_and_blank_lines() {
+        assert!(matches!(Line::parse("# Sprint 42", Some("#")), Line::Comment(_)));
+        assert!(matches!(Line::parse("   ", Some("#")), Line::Blank(_)));
+        assert!(matches!(Line::parse("", Some("#")), Line::Blank(_)));
+        assert_eq!(Line::parse("(A) Task", Some("#")).task().expect("Must be a task").priority, Some('A'));
+    }
+
+    #[test]
+    fn parse_respects_the_comment_prefix() {
+        assert!(Line::parse("# Not a comment", Some("//")).task().is_some());
+        assert!(Line::parse("# Not a comment", None).task().is_some());
+        assert!(Line::parse("# Not a comment", Some("")).task().is_some());
+        assert!(Line::parse("// Comment", Some("//")).task().is_none());
+    }
+
+    #[test]
+    fn lines_round_trip() {
+        for input in &["# Sprint 42", "", " \t ", "x 2018-11-25 2018-11-24 Done"] {
+            assert_eq!(Line::parse(input, Some("#")).to_string(), *input);
+        }
+    }
+
+    #[test]
+    fn comment_text_strips_the_prefix() {
+        assert_eq!(Line::parse("#  Sprint 42 ", Some("#")).comment_text("#"), Some("Sprint 42"));
+        assert_eq!(Line::parse("Task", Some("#")).comment_text("#"), None);
+    }
+}
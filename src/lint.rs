@@ -0,0 +1,402 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Error;
+use std::fs;
+use std::io;
+use std::path::Path;
+use toml;
+use description_component::DescriptionComponent;
+use line::Line;
+use parsers::*;
+use task::DATE_KEYS;
+use task::Task;
+use task_list::TaskList;
+
+/// The checks of the linter, named like `duplicate-task` in configs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Rule {
+    /// A completed task with one date, which the spec reads as the completion date.
+    CompletionWithoutCreation,
+    CompletionBeforeCreation,
+    PriorityOnCompleted,
+    /// A leading date or a date option like `due:2018-02-30` that isn't in the calendar.
+    ImpossibleDate,
+    /// The same task as on an earlier line.
+    DuplicateTask,
+    /// An option key that isn't in the known keys, a team policy that is off by default.
+    UnknownKey,
+}
+
+impl Rule {
+    pub const ALL : [Rule; 6] = [
+        Rule::CompletionWithoutCreation,
+        Rule::CompletionBeforeCreation,
+        Rule::PriorityOnCompleted,
+        Rule::ImpossibleDate,
+        Rule::DuplicateTask,
+        Rule::UnknownKey,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Rule::CompletionWithoutCreation => "completion-without-creation",
+            Rule::CompletionBeforeCreation => "completion-before-creation",
+            Rule::PriorityOnCompleted => "priority-on-completed",
+            Rule::ImpossibleDate => "impossible-date",
+            Rule::DuplicateTask => "duplicate-task",
+            Rule::UnknownKey => "unknown-key",
+        }
+    }
+
+    pub fn parse(name : &str) -> Option<Rule> {
+        Rule::ALL.iter().cloned().find(|rule| rule.name() == name)
+    }
+
+    fn default_severity(self) -> Severity {
+        match self {
+            Rule::CompletionWithoutCreation | Rule::CompletionBeforeCreation | Rule::ImpossibleDate => Severity::Error,
+            Rule::PriorityOnCompleted | Rule::DuplicateTask => Severity::Warning,
+            Rule::UnknownKey => Severity::Off,
+        }
+    }
+}
+
+/// How bad a problem is, `Off` turns a rule off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Off,
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    pub fn parse(name : &str) -> Option<Severity> {
+        match name {
+            "off" => Some(Severity::Off),
+            "info" => Some(Severity::Info),
+            "warning" => Some(Severity::Warning),
+            "error" => Some(Severity::Error),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Severity {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
+        write!(formatter, "{}", match self {
+            Severity::Off => "off",
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RuleConfig {
+    pub severity: Severity,
+    /// Whether `Linter::fix` may fix the problems of the rule, if the rule has a fix.
+    pub fix: bool,
+}
+
+/// A problem found on a line of a list.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Problem {
+    pub line: usize,
+    pub rule: Rule,
+    pub severity: Severity,
+    pub message: String,
+    fix: Option<Fix>,
+}
+
+impl Problem {
+    pub fn is_fixable(&self) -> bool {
+        self.fix.is_some()
+    }
+}
+
+impl Display for Problem {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
+        write!(formatter, "{}: {}: {} [{}]", self.line, self.severity, self.message, self.rule.name())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Fix {
+    Replace(String),
+    Remove,
+}
+
+#[derive(Debug)]
+pub enum LintError {
+    Io(io::Error),
+    Toml(toml::de::Error),
+    /// A setting of the `lint` table that isn't understood, with the reason.
+    Setting(String, String),
+}
+
+impl Display for LintError {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
+        match self {
+            LintError::Io(error) => write!(formatter, "Couldn't read the lint rules: {}", error),
+            LintError::Toml(error) => write!(formatter, "Couldn't parse the lint rules: {}", error),
+            LintError::Setting(name, reason) => write!(formatter, "Invalid lint setting '{}': {}", name, reason),
+        }
+    }
+}
+
+impl From<io::Error> for LintError {
+    fn from(error : io::Error) -> LintError {
+        LintError::Io(error)
+    }
+}
+
+impl From<toml::de::Error> for LintError {
+    fn from(error : toml::de::Error) -> LintError {
+        LintError::Toml(error)
+    }
+}
+
+#[derive(Deserialize)]
+struct LintConfig {
+    #[serde(default)]
+    lint: BTreeMap<String, toml::Value>,
+}
+
+/// Checks lists against the todo.txt spec and team policies.
+///
+/// The rules are configured in the `lint` table of a TOML config, by severity alone
+/// or with whether they may be fixed:
+///
+/// ```toml
+/// [lint]
+/// known_keys = ["due", "t", "owner"]
+/// unknown-key = "warning"
+/// duplicate-task = { severity = "error", fix = false }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Linter {
+    pub rules: BTreeMap<Rule, RuleConfig>,
+    /// The option keys the `unknown-key` rule accepts.
+    pub known_keys: BTreeSet<String>,
+}
+
+impl Default for Linter {
+    /// The spec rules on with their fixes, `due`, `t`, `rec`, `h` and `pri` as known keys.
+    fn default() -> Linter {
+        Linter {
+            rules: Rule::ALL.iter().map(|&rule| (rule, RuleConfig { severity: rule.default_severity(), fix: true })).collect(),
+            known_keys: DATE_KEYS.iter().chain(["rec", "h", "pri"].iter()).map(|&key| String::from(key)).collect(),
+        }
+    }
+}
+
+impl Linter {
+    /// The default rules changed by the `lint` table of a TOML config. Other tables are ignored.
+    pub fn parse_config(input : &str) -> Result<Linter, LintError> {
+        let config : LintConfig = toml::from_str(input)?;
+        let mut linter = Linter::default();
+        for (name, value) in &config.lint {
+            let invalid = |reason : &str| LintError::Setting(name.clone(), String::from(reason));
+            if name == "known_keys" {
+                linter.known_keys = value.as_array()
+                    .and_then(|keys| keys.iter().map(|key| key.as_str().map(String::from)).collect())
+                    .ok_or_else(|| invalid("Expected a list of keys."))?;
+                continue;
+            }
+            let rule = Rule::parse(name).ok_or_else(|| invalid("Unknown rule."))?;
+            let config = linter.rules.get_mut(&rule).expect("All rules are configured.");
+            let (severity, fix) = match value {
+                toml::Value::String(severity) => (Some(severity.as_str()), None),
+                toml::Value::Table(table) => (
+                    table.get("severity").map(|severity| severity.as_str().ok_or_else(|| invalid("Expected a severity.")))
+                        .transpose()?,
+                    table.get("fix").map(|fix| fix.as_bool().ok_or_else(|| invalid("Expected true or false for fix.")))
+                        .transpose()?,
+                ),
+                _ => return Err(invalid("Expected a severity or a table.")),
+            };
+            if let Some(severity) = severity {
+                config.severity = Severity::parse(severity).ok_or_else(|| invalid("Expected off, info, warning or error."))?;
+            }
+            if let Some(fix) = fix {
+                config.fix = fix;
+            }
+        }
+        Ok(linter)
+    }
+
+    pub fn load_config<P: AsRef<Path>>(path : P) -> Result<Linter, LintError> {
+        Linter::parse_config(&fs::read_to_string(path)?)
+    }
+
+    /// The problems of the tasks of a list, ordered by line.
+    pub fn lint(&self, list : &TaskList) -> Vec<Problem> {
+        let mut seen = HashMap::new();
+        let mut problems = Vec::new();
+        for (index, line) in list.lines().iter().enumerate() {
+            let task = match line {
+                Line::Task(task) => task,
+                _ => continue,
+            };
+            let text = task.to_string();
+            match seen.get(&text) {
+                Some(first) => self.report(&mut problems, index + 1, Rule::DuplicateTask, format!("Same task as on line {}.", first), Some(Fix::Remove)),
+                None => {
+                    seen.insert(text, index + 1);
+                },
+            }
+            self.check_task(&mut problems, index + 1, task);
+        }
+        problems.sort_by_key(|problem| problem.line);
+        problems
+    }
+
+    /// Applies the fixes of the rules that may be fixed and returns the fixed problems.
+    /// Duplicates are removed, so later lines move up.
+    pub fn fix(&self, list : &mut TaskList) -> Vec<Problem> {
+        let problems = self.lint(list).into_iter()
+            .filter(|problem| problem.fix.is_some() && self.rules[&problem.rule].fix)
+            .collect::<Vec<Problem>>();
+
+        //one fix per line, the first one, the others are found again by the next lint
+        let mut fixed = Vec::new();
+        for problem in &problems {
+            if fixed.iter().any(|fixed : &Problem| fixed.line == problem.line) {
+                continue;
+            }
+            if let Some(Fix::Replace(ref text)) = problem.fix {
+                list.replace(problem.line, Task::new(text));
+            }
+            fixed.push(problem.clone());
+        }
+        for problem in fixed.iter().rev() {
+            if problem.fix == Some(Fix::Remove) {
+                list.remove(problem.line);
+            }
+        }
+        fixed
+    }
+
+    fn check_task(&self, problems : &mut Vec<Problem>, line : usize, task : &Task) {
+        match (task.completed, task.completed_at(), task.created_at()) {
+            (true, Some(date), None) => {
+                let mut fixed = task.clone();
+                fixed.set_created_at(Some(date));
+                self.report(problems, line, Rule::CompletionWithoutCreation, String::from("Completion date without a creation date."),
+                    Some(Fix::Replace(fixed.to_string())));
+            },
+            (true, Some(completed_at), Some(created_at)) if completed_at < created_at => {
+                let mut fixed = task.clone();
+                fixed.set_created_at(Some(completed_at));
+                fixed.set_completed_at(Some(created_at));
+                self.report(problems, line, Rule::CompletionBeforeCreation,
+                    format!("Completed on {} before it was created on {}.", completed_at.format("%F"), created_at.format("%F")),
+                    Some(Fix::Replace(fixed.to_string())));
+            },
+            _ => {},
+        }
+
+        if task.completed && task.priority.is_some() {
+            let mut fixed = task.clone();
+            fixed.priority = None;
+            self.report(problems, line, Rule::PriorityOnCompleted, String::from("Completed task with a priority."), Some(Fix::Replace(fixed.to_string())));
+        }
+
+        //invalid leading dates end up in the description
+        let leading = task.description().split(' ').next().map(String::from).unwrap_or_default();
+        if is_impossible_date(&leading) {
+            self.report(problems, line, Rule::ImpossibleDate, format!("{} isn't a date.", leading), None);
+        }
+        for component in task.description_components() {
+            if let DescriptionComponent::KeyValue(key, value) = component {
+                if DATE_KEYS.contains(&key.as_str()) && is_impossible_date(value) {
+                    self.report(problems, line, Rule::ImpossibleDate, format!("{}:{} isn't a date.", key, value), None);
+                }
+                if !self.known_keys.contains(key) {
+                    self.report(problems, line, Rule::UnknownKey, format!("Unknown key '{}'.", key), None);
+                }
+            }
+        }
+    }
+
+    fn report(&self, problems : &mut Vec<Problem>, line : usize, rule : Rule, message : String, fix : Option<Fix>) {
+        let severity = self.rules[&rule].severity;
+        if severity != Severity::Off {
+            problems.push(Problem { line, rule, severity, message, fix });
+        }
+    }
+}
+
+//shaped like an ISO date, but not in the calendar
+fn is_impossible_date(text : &str) -> bool {
+    let bytes = text.as_bytes();
+    let shaped = bytes.len() == 10 && bytes.iter().enumerate().all(|(index, byte)| match index {
+        4 | 7 => *byte == b'-',
+        _ => byte.is_ascii_digit(),
+    });
+    shaped && DateParser::parse(text).is_none()
+}
+
+#[cfg(test)]
+mod tests {
+    use lint::*;
+
+    const TODO : &str = "x 2018-11-24 Pay rent\nx 2018-11-20 2018-11-22 Fix bug\nx (A) 2018-11-24 2018-11-20 Call mom\n2018-02-30 Plan due:2018-13-01 owner:max\n\nPlan due:2018-13-01 owner:max\nPay rent\n";
+
+    fn summary(problems : &[Problem]) -> Vec<(usize, &'static str, Severity)> {
+        problems.iter().map(|problem| (problem.line, problem.rule.name(), problem.severity)).collect()
+    }
+
+    #[test]
+    fn finds_spec_violations_and_duplicates() {
+        let list = TaskList::parse("todo.txt", &format!("{}2018-02-30 Plan due:2018-13-01 owner:max\n", TODO));
+        let problems = Linter::default().lint(&list);
+
+        assert_eq!(summary(&problems), vec![
+            (1, "completion-without-creation", Severity::Error),
+            (2, "completion-before-creation", Severity::Error),
+            (3, "priority-on-completed", Severity::Warning),
+            (4, "impossible-date", Severity::Error),
+            (4, "impossible-date", Severity::Error),
+            (6, "impossible-date", Severity::Error),
+            (8, "duplicate-task", Severity::Warning),
+            (8, "impossible-date", Severity::Error),
+            (8, "impossible-date", Severity::Error),
+        ]);
+        assert_eq!(problems[0].to_string(), "1: error: Completion date without a creation date. [completion-without-creation]");
+        assert_eq!(problems[6].message, "Same task as on line 4.");
+    }
+
+    #[test]
+    fn rules_are_configured_in_toml() {
+        let linter = Linter::parse_config("[lint]\nknown_keys = [\"due\"]\nunknown-key = \"info\"\nimpossible-date = \"off\"\npriority-on-completed = { fix = false }\n")
+            .expect("Must parse.");
+        let problems = linter.lint(&TaskList::parse("todo.txt", TODO));
+
+        assert_eq!(summary(&problems)[3..], [(4, "unknown-key", Severity::Info), (6, "unknown-key", Severity::Info)]);
+        assert!(!linter.rules[&Rule::PriorityOnCompleted].fix);
+
+        let error = Linter::parse_config("[lint]\nsloppy-tasks = \"error\"").expect_err("Must not parse.");
+        assert_eq!(error.to_string(), "Invalid lint setting 'sloppy-tasks': Unknown rule.");
+        assert!(Linter::parse_config("[lint]\nunknown-key = \"fatal\"").is_err());
+    }
+
+    #[test]
+    fn fixes_what_can_be_fixed() {
+        let mut list = TaskList::parse("todo.txt", "x 2018-11-24 Pay rent\nx (A) 2018-11-20 2018-11-22 Fix bug\nCall mom\nCall mom\n");
+        let fixed = Linter::default().fix(&mut list);
+
+        assert_eq!(summary(&fixed).iter().map(|&(line, rule, _)| (line, rule)).collect::<Vec<(usize, &str)>>(),
+            vec![(1, "completion-without-creation"), (2, "completion-before-creation"), (4, "duplicate-task")]);
+        assert_eq!(list.to_string(), "x 2018-11-24 2018-11-24 Pay rent\nx (A) 2018-11-22 2018-11-20 Fix bug\nCall mom\n");
+
+        Linter::default().fix(&mut list);
+        assert_eq!(list.to_string(), "x 2018-11-24 2018-11-24 Pay rent\nx 2018-11-22 2018-11-20 Fix bug\nCall mom\n");
+        assert!(Linter::default().lint(&list).is_empty());
+    }
+}
@@ -1,4 +1,5 @@
 use chrono::prelude::*;
+use chrono::Duration;
 use regex::*;
 
 pub struct ParserResult<'a, Value> {
@@ -61,6 +62,181 @@ impl Parser for CompletionMarkParser {
     }
 }
 
+pub struct PriorityParser {}
+
+impl Parser for PriorityParser {
+    type Value = Option<char>;
+
+    fn parse(input: &str) -> Option<ParserResult<Self::Value>> {
+        let bytes = input.as_bytes();
+        if bytes.len() >= 4 && bytes[0] == b'(' && bytes[1].is_ascii_uppercase() && bytes[2] == b')' && bytes[3] == b' ' {
+            Some(ParserResult {
+                value: Some(bytes[1] as char),
+                remaining: &input[4..],
+            })
+        } else {
+            Some(ParserResult {
+                value: None,
+                remaining: input,
+            })
+        }
+    }
+}
+
+/// Parses a date at the start of a task line. The date has to be followed by
+/// a space, otherwise it is considered to be part of the description.
+pub struct DateFieldParser {}
+
+impl Parser for DateFieldParser {
+    type Value = Option<Date<Utc>>;
+
+    fn parse(input: &str) -> Option<ParserResult<Self::Value>> {
+        match DateParser::parse(input) {
+            Some(ref date_result) if date_result.remaining.starts_with(' ') => Some(ParserResult {
+                value: Some(date_result.value),
+                remaining: &date_result.remaining[1..],
+            }),
+            _ => Some(ParserResult {
+                value: None,
+                remaining: input,
+            })
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DateUnit {
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+/// A date that may be relative to a reference day, see `RelativeDateParser`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DateValue {
+    Absolute(Date<Utc>),
+    Relative(i64, DateUnit),
+    /// The next such weekday after the reference day.
+    Weekday(Weekday),
+    /// The last day of the reference day's week, month or year.
+    EndOf(DateUnit),
+}
+
+impl DateValue {
+    pub fn resolve(&self, today : Date<Utc>) -> Date<Utc> {
+        match *self {
+            DateValue::Absolute(date) => date,
+            DateValue::Relative(amount, DateUnit::Day) => today + Duration::days(amount),
+            DateValue::Relative(amount, DateUnit::Week) => today + Duration::weeks(amount),
+            DateValue::Relative(amount, DateUnit::Month) => add_months(today, amount),
+            DateValue::Relative(amount, DateUnit::Year) => add_months(today, amount * 12),
+            DateValue::Weekday(weekday) => {
+                let days_ahead = (7 + weekday.num_days_from_monday() as i64 - today.weekday().num_days_from_monday() as i64) % 7;
+                today + Duration::days(if days_ahead == 0 { 7 } else { days_ahead })
+            },
+            DateValue::EndOf(DateUnit::Day) => today,
+            DateValue::EndOf(DateUnit::Week) => today + Duration::days(6 - today.weekday().num_days_from_monday() as i64),
+            DateValue::EndOf(DateUnit::Month) => add_months(today.with_day(1).expect("Every month has a first day."), 1) - Duration::days(1),
+            DateValue::EndOf(DateUnit::Year) => Utc.ymd(today.year(), 12, 31),
+        }
+    }
+
+    /// Parses a whole string with `RelativeDateParser`.
+    pub fn parse(input : &str) -> Option<DateValue> {
+        match RelativeDateParser::parse(input) {
+            Some(ref date_result) if date_result.remaining.is_empty() => Some(date_result.value),
+            _ => None,
+        }
+    }
+}
+
+/// Adds months to a date, days that don't exist in the target month are clamped to its last day.
+pub fn add_months(date : Date<Utc>, months : i64) -> Date<Utc> {
+    let month_index = i64::from(date.year()) * 12 + i64::from(date.month0()) + months;
+    let year = month_index.div_euclid(12) as i32;
+    let month = month_index.rem_euclid(12) as u32 + 1;
+    (0..4).filter_map(|days_back| Utc.ymd_opt(year, month, date.day() - days_back).latest())
+        .next()
+        .expect("Every month has at least 28 days.")
+}
+
+/// Parses ISO dates like `DateParser` and dates relative to a reference day:
+/// `today`, `tomorrow`, `yesterday`, offsets like `+2w`, `3d` or `today-1m` (units are
+/// `d`, `w`, `m` and `y`, offsets without a unit need a sign), weekdays like `fri` or `friday` and `eow`, `eom` and `eoy`.
+/// Relative dates have to be followed by whitespace or the end of the input.
+pub struct RelativeDateParser {}
+
+impl Parser for RelativeDateParser {
+    type Value = DateValue;
+
+    fn parse(input: &str) -> Option<ParserResult<Self::Value>> {
+        if let Some(date_result) = DateParser::parse(input) {
+            return Some(ParserResult {
+                value: DateValue::Absolute(date_result.value),
+                remaining: date_result.remaining,
+            });
+        }
+
+        let length = input.find(char::is_whitespace).unwrap_or(input.len());
+        let word = input[..length].to_lowercase();
+        let value = match word.as_str() {
+            "today" => DateValue::Relative(0, DateUnit::Day),
+            "tomorrow" => DateValue::Relative(1, DateUnit::Day),
+            "yesterday" => DateValue::Relative(-1, DateUnit::Day),
+            "eod" => DateValue::EndOf(DateUnit::Day),
+            "eow" => DateValue::EndOf(DateUnit::Week),
+            "eom" => DateValue::EndOf(DateUnit::Month),
+            "eoy" => DateValue::EndOf(DateUnit::Year),
+            _ => match parse_weekday(&word) {
+                Some(weekday) => DateValue::Weekday(weekday),
+                None => parse_offset(word.strip_prefix("today").unwrap_or(&word))?,
+            }
+        };
+        Some(ParserResult {
+            value,
+            remaining: &input[length..],
+        })
+    }
+}
+
+fn parse_weekday(word : &str) -> Option<Weekday> {
+    const WEEKDAYS : [(&str, &str, Weekday); 7] = [
+        ("mon", "monday", Weekday::Mon),
+        ("tue", "tuesday", Weekday::Tue),
+        ("wed", "wednesday", Weekday::Wed),
+        ("thu", "thursday", Weekday::Thu),
+        ("fri", "friday", Weekday::Fri),
+        ("sat", "saturday", Weekday::Sat),
+        ("sun", "sunday", Weekday::Sun),
+    ];
+    WEEKDAYS.iter()
+        .find(|&&(short, long, _)| word == short || word == long)
+        .map(|&(_, _, weekday)| weekday)
+}
+
+fn parse_offset(offset : &str) -> Option<DateValue> {
+    let (sign, offset) = match offset.chars().next()? {
+        '+' => (Some(1), &offset[1..]),
+        '-' => (Some(-1), &offset[1..]),
+        _ => (None, offset),
+    };
+    let unit_position = offset.find(|character : char| !character.is_ascii_digit()).unwrap_or(offset.len());
+    if unit_position == 0 {
+        return None;
+    }
+    let unit = match &offset[unit_position..] {
+        //a bare number like due:5 or due:2018 isn't a date, offsets need a sign or a unit
+        "" if sign.is_none() => return None,
+        "" | "d" => DateUnit::Day,
+        "w" => DateUnit::Week,
+        "m" => DateUnit::Month,
+        "y" => DateUnit::Year,
+        _ => return None,
+    };
+    Some(DateValue::Relative(sign.unwrap_or(1) * offset[..unit_position].parse::<i64>().ok()?, unit))
+}
+
 
 #[cfg(test)]
 mod tests {
@@ -127,4 +303,92 @@ mod tests {
         assert_eq!(parse_result.value, false);
         assert_eq!(parse_result.remaining, "");
     }
+
+    #[test]
+    fn priority_parser_should_parse_priorities() {
+        let parse_result = PriorityParser::parse("(A) Some text").expect("Must parse");
+
+        assert_eq!(parse_result.value, Some('A'));
+        assert_eq!(parse_result.remaining, "Some text");
+    }
+
+    #[test]
+    fn priority_parser_should_not_parse_invalid_priorities() {
+        for input in &["(a) Some text", "(A)Some text", "A) Some text", " (A) Some text", "(A)"] {
+            let parse_result = PriorityParser::parse(input).expect("Must parse");
+
+            assert_eq!(parse_result.value, None);
+            assert_eq!(parse_result.remaining, *input);
+        }
+    }
+
+    #[test]
+    fn date_field_parser_should_parse_dates_followed_by_a_space() {
+        let parse_result = DateFieldParser::parse("2018-11-24 Some text").expect("Must parse");
+
+        assert_eq!(parse_result.value, Some(Utc.ymd(2018, 11, 24)));
+        assert_eq!(parse_result.remaining, "Some text");
+    }
+
+    #[test]
+    fn date_field_parser_should_not_parse_dates_without_a_space() {
+        let parse_result = DateFieldParser::parse("2018-11-24").expect("Must parse");
+
+        assert_eq!(parse_result.value, None);
+        assert_eq!(parse_result.remaining, "2018-11-24");
+    }
+
+    #[test]
+    fn relative_date_parser_should_parse_words_and_offsets() {
+        let cases = [
+            ("today", DateValue::Relative(0, DateUnit::Day)),
+            ("Tomorrow", DateValue::Relative(1, DateUnit::Day)),
+            ("yesterday", DateValue::Relative(-1, DateUnit::Day)),
+            ("+2w", DateValue::Relative(2, DateUnit::Week)),
+            ("3d", DateValue::Relative(3, DateUnit::Day)),
+            ("-5", DateValue::Relative(-5, DateUnit::Day)),
+            ("today-1m", DateValue::Relative(-1, DateUnit::Month)),
+            ("fri", DateValue::Weekday(Weekday::Fri)),
+            ("monday", DateValue::Weekday(Weekday::Mon)),
+            ("eom", DateValue::EndOf(DateUnit::Month)),
+            ("2018-11-24", DateValue::Absolute(Utc.ymd(2018, 11, 24))),
+        ];
+
+        for &(input, expected) in cases.iter() {
+            assert_eq!(DateValue::parse(input), Some(expected), "{}", input);
+        }
+    }
+
+    #[test]
+    fn relative_date_parser_should_stop_at_whitespace() {
+        let parse_result = RelativeDateParser::parse("tomorrow and more").expect("Must parse");
+
+        assert_eq!(parse_result.value, DateValue::Relative(1, DateUnit::Day));
+        assert_eq!(parse_result.remaining, " and more");
+    }
+
+    #[test]
+    fn relative_date_parser_should_not_parse_other_words() {
+        for input in &["tomorrowland", "soon", "+", "today+3x", "fr", "w", "5", "2018", "today5"] {
+            assert!(RelativeDateParser::parse(input).is_none(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn relative_dates_resolve_against_the_reference_day() {
+        //a Saturday
+        let today = Utc.ymd(2018, 11, 24);
+        let resolve = |input : &str| DateValue::parse(input).expect("Must parse").resolve(today);
+
+        assert_eq!(resolve("today"), today);
+        assert_eq!(resolve("tomorrow"), Utc.ymd(2018, 11, 25));
+        assert_eq!(resolve("today-2w"), Utc.ymd(2018, 11, 10));
+        assert_eq!(resolve("+1y"), Utc.ymd(2019, 11, 24));
+        assert_eq!(resolve("fri"), Utc.ymd(2018, 11, 30));
+        assert_eq!(resolve("sat"), Utc.ymd(2018, 12, 1));
+        assert_eq!(resolve("eow"), Utc.ymd(2018, 11, 25));
+        assert_eq!(resolve("eom"), Utc.ymd(2018, 11, 30));
+        assert_eq!(resolve("eoy"), Utc.ymd(2018, 12, 31));
+        assert_eq!(DateValue::parse("+1m").expect("Must parse").resolve(Utc.ymd(2019, 1, 31)), Utc.ymd(2019, 2, 28));
+    }
 }
@@ -0,0 +1,517 @@
+use chrono::prelude::*;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Error;
+use parsers::*;
+use task::Task;
+
+/// A filter written in the query language, e.g.
+/// `+rustodo and @office and not done and (pri>=B or due<=today+3d) and owner:max`.
+///
+/// * `+project`, `@context` and `done` match tasks with that project, context or completion.
+/// * `key:value` and `key=value` match options, `!=` negates the comparison.
+/// * `section:"Sprint 42"` matches the tasks under the comment `# Sprint 42`, see
+///   `TaskList::section`. Tasks without a known section are in none.
+/// * `pri`, `created`, `completed`, `due` and `t` can be compared with `<`, `<=`, `>` and `>=`.
+///   Priority `A` is the highest, so `pri>=B` matches `A` and `B`. Dates are either
+///   ISO dates or relative to today, like `today+3d` or `fri`, see `RelativeDateParser`.
+/// * Other words and quoted strings match the description, ignoring case.
+/// * Terms are combined with `and`, `or`, `not` and parentheses, `and` binds stronger than `or`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    expression: Expression,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+    And(Box<Expression>, Box<Expression>),
+    Or(Box<Expression>, Box<Expression>),
+    Not(Box<Expression>),
+    Done,
+    Project(String),
+    Context(String),
+    Text(String),
+    Priority(Operator, Option<char>),
+    Date(DateField, Operator, DateValue),
+    Option(String, Operator, String),
+    Section(Operator, String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operator {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DateField {
+    Created,
+    Completed,
+    Option(String),
+}
+
+/// A query that couldn't be parsed, `position` is the byte offset of the problem.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryError {
+    pub position: usize,
+    pub message: String,
+}
+
+impl QueryError {
+    fn new(position : usize, message : &str) -> QueryError {
+        QueryError {
+            position,
+            message: String::from(message),
+        }
+    }
+
+    /// The query with a caret below the position of the error.
+    pub fn highlight(&self, query : &str) -> String {
+        let offset = query[..self.position.min(query.len())].chars().count();
+        format!("{}\n{}^ {}", query, " ".repeat(offset), self.message)
+    }
+}
+
+impl Display for QueryError {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
+        write!(formatter, "{} (at position {})", self.message, self.position)
+    }
+}
+
+impl Query {
+    pub fn parse(input : &str) -> Result<Query, QueryError> {
+        let tokens = tokenize(input)?;
+        let mut parser = QueryParser {
+            tokens: &tokens,
+            index: 0,
+            end: input.len(),
+        };
+        let expression = parser.parse_or()?;
+        match parser.peek() {
+            Some(token) => Err(QueryError::new(token.position, match token.kind {
+                TokenKind::RightParen => "Unmatched ')'.",
+                _ => "Expected 'and' or 'or' between filters.",
+            })),
+            None => Ok(Query { expression }),
+        }
+    }
+
+    pub fn expression(&self) -> &Expression {
+        &self.expression
+    }
+
+    /// Turns the query into a predicate, relative dates are resolved against `today`.
+    /// The tasks aren't in any section.
+    pub fn compile(&self, today : Date<Utc>) -> Box<dyn Fn(&Task) -> bool> {
+        compile(&self.expression, today)
+    }
+
+    /// Whether a task that isn't in any section matches.
+    pub fn matches(&self, task : &Task, today : Date<Utc>) -> bool {
+        evaluate(&self.expression, task, None, today)
+    }
+
+    /// Whether a task in a section, e.g. from `TaskList::sectioned_tasks`, matches.
+    pub fn matches_in_section(&self, task : &Task, section : Option<&str>, today : Date<Utc>) -> bool {
+        evaluate(&self.expression, task, section, today)
+    }
+}
+
+impl Operator {
+    fn compare<T: PartialOrd>(self, left : T, right : T) -> bool {
+        match self {
+            Operator::Equal => left == right,
+            Operator::NotEqual => left != right,
+            Operator::Less => left < right,
+            Operator::LessOrEqual => left <= right,
+            Operator::Greater => left > right,
+            Operator::GreaterOrEqual => left >= right,
+        }
+    }
+
+    fn is_ordering(self) -> bool {
+        !matches!(self, Operator::Equal | Operator::NotEqual)
+    }
+}
+
+//A ranks highest, tasks without a valid priority rank lowest
+fn priority_rank(priority : Option<char>) -> u32 {
+    match priority {
+        Some(priority @ 'A'..='Z') => 26 - (priority as u32 - 'A' as u32),
+        _ => 0,
+    }
+}
+
+fn evaluate(expression : &Expression, task : &Task, section : Option<&str>, today : Date<Utc>) -> bool {
+    match expression {
+        Expression::And(left, right) => evaluate(left, task, section, today) && evaluate(right, task, section, today),
+        Expression::Or(left, right) => evaluate(left, task, section, today) || evaluate(right, task, section, today),
+        Expression::Not(inner) => !evaluate(inner, task, section, today),
+        Expression::Done => task.completed,
+        Expression::Project(project) => task.projects().contains(project),
+        Expression::Context(context) => task.contexts().contains(context),
+        Expression::Text(text) => task.description().to_lowercase().contains(&text.to_lowercase()),
+        Expression::Priority(operator, priority) => operator.compare(priority_rank(task.priority), priority_rank(*priority)),
+        Expression::Date(field, operator, value) => {
+            let date = match field {
+                DateField::Created => task.created_at(),
+                DateField::Completed => task.completed_at(),
+                DateField::Option(key) => task.options().get(key)
+                    .and_then(|value| DateValue::parse(value))
+                    .map(|value| value.resolve(today)),
+            };
+            match date {
+                Some(date) => operator.compare(date, value.resolve(today)),
+                None => false,
+            }
+        },
+        Expression::Option(key, operator, value) => match task.options().get(key) {
+            Some(option) if operator.is_ordering() => match (option.parse::<f64>(), value.parse::<f64>()) {
+                (Ok(option), Ok(value)) => operator.compare(option, value),
+                _ => false,
+            },
+            Some(option) => operator.compare(option, value),
+            None => *operator == Operator::NotEqual,
+        },
+        Expression::Section(operator, title) => operator.compare(section, Some(title.as_str())),
+    }
+}
+
+fn compile(expression : &Expression, today : Date<Utc>) -> Box<dyn Fn(&Task) -> bool> {
+    match expression.clone() {
+        Expression::And(left, right) => {
+            let (left, right) = (compile(&left, today), compile(&right, today));
+            Box::new(move |task| left(task) && right(task))
+        },
+        Expression::Or(left, right) => {
+            let (left, right) = (compile(&left, today), compile(&right, today));
+            Box::new(move |task| left(task) || right(task))
+        },
+        Expression::Not(inner) => {
+            let inner = compile(&inner, today);
+            Box::new(move |task| !inner(task))
+        },
+        leaf => Box::new(move |task| evaluate(&leaf, task, None, today)),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    LeftParen,
+    RightParen,
+    And,
+    Or,
+    Not,
+    Operator(Operator),
+    Word(String),
+    Quoted(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Token {
+    kind: TokenKind,
+    position: usize,
+}
+
+const OPERATOR_CHARACTERS : &str = "<>=!:";
+
+fn tokenize(input : &str) -> Result<Vec<Token>, QueryError> {
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+    while let Some((position, character)) = chars.next() {
+        let kind = match character {
+            _ if character.is_whitespace() => continue,
+            '(' => TokenKind::LeftParen,
+            ')' => TokenKind::RightParen,
+            '"' => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '"')) => break,
+                        Some((_, character)) => text.push(character),
+                        None => return Err(QueryError::new(position, "Unterminated string.")),
+                    }
+                }
+                TokenKind::Quoted(text)
+            },
+            _ if OPERATOR_CHARACTERS.contains(character) => {
+                let followed_by_equal = chars.peek().map(|&(_, next)| next) == Some('=');
+                let operator = match (character, followed_by_equal) {
+                    ('<', true) => Operator::LessOrEqual,
+                    ('>', true) => Operator::GreaterOrEqual,
+                    ('!', true) => Operator::NotEqual,
+                    ('<', false) => Operator::Less,
+                    ('>', false) => Operator::Greater,
+                    ('=', _) | (':', _) => Operator::Equal,
+                    _ => return Err(QueryError::new(position, "Expected '!='.")),
+                };
+                if followed_by_equal && character != '=' && character != ':' {
+                    chars.next();
+                }
+                TokenKind::Operator(operator)
+            },
+            _ => {
+                let mut word = character.to_string();
+                while let Some(&(_, next)) = chars.peek() {
+                    if next.is_whitespace() || "()\"".contains(next) || OPERATOR_CHARACTERS.contains(next) {
+                        break;
+                    }
+                    word.push(next);
+                    chars.next();
+                }
+                match word.as_str() {
+                    "and" => TokenKind::And,
+                    "or" => TokenKind::Or,
+                    "not" => TokenKind::Not,
+                    _ => TokenKind::Word(word),
+                }
+            },
+        };
+        tokens.push(Token { kind, position });
+    }
+    Ok(tokens)
+}
+
+struct QueryParser<'a> {
+    tokens: &'a [Token],
+    index: usize,
+    end: usize,
+}
+
+impl<'a> QueryParser<'a> {
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.index)
+    }
+
+    fn next(&mut self) -> Option<&'a Token> {
+        let token = self.tokens.get(self.index);
+        self.index += 1;
+        token
+    }
+
+    fn parse_or(&mut self) -> Result<Expression, QueryError> {
+        let mut expression = self.parse_and()?;
+        while self.peek().map(|token| &token.kind) == Some(&TokenKind::Or) {
+            self.next();
+            expression = Expression::Or(Box::new(expression), Box::new(self.parse_and()?));
+        }
+        Ok(expression)
+    }
+
+    fn parse_and(&mut self) -> Result<Expression, QueryError> {
+        let mut expression = self.parse_not()?;
+        while self.peek().map(|token| &token.kind) == Some(&TokenKind::And) {
+            self.next();
+            expression = Expression::And(Box::new(expression), Box::new(self.parse_not()?));
+        }
+        Ok(expression)
+    }
+
+    fn parse_not(&mut self) -> Result<Expression, QueryError> {
+        if self.peek().map(|token| &token.kind) == Some(&TokenKind::Not) {
+            self.next();
+            return Ok(Expression::Not(Box::new(self.parse_not()?)));
+        }
+        self.parse_term()
+    }
+
+    fn parse_term(&mut self) -> Result<Expression, QueryError> {
+        let token = match self.next() {
+            Some(token) => token,
+            None => return Err(QueryError::new(self.end, "Expected a filter.")),
+        };
+        match token.kind {
+            TokenKind::LeftParen => {
+                let expression = self.parse_or()?;
+                match self.next() {
+                    Some(&Token { kind: TokenKind::RightParen, .. }) => Ok(expression),
+                    _ => Err(QueryError::new(token.position, "Unclosed '('.")),
+                }
+            },
+            TokenKind::Quoted(ref text) => Ok(Expression::Text(text.clone())),
+            TokenKind::Word(ref word) => match self.peek() {
+                Some(&Token { kind: TokenKind::Operator(operator), position }) => {
+                    self.next();
+                    match self.next() {
+                        Some(&Token { kind: TokenKind::Word(ref value), position: value_position })
+                            | Some(&Token { kind: TokenKind::Quoted(ref value), position: value_position }) =>
+                            comparison(word, token.position, operator, value, value_position),
+                        Some(token) => Err(QueryError::new(token.position, "Expected a value.")),
+                        None => Err(QueryError::new(position + 1, "Expected a value.")),
+                    }
+                },
+                _ => word_term(word, token.position),
+            },
+            TokenKind::Operator(_) => Err(QueryError::new(token.position, "Expected a field name before the comparison.")),
+            TokenKind::RightParen => Err(QueryError::new(token.position, "Unmatched ')'.")),
+            _ => Err(QueryError::new(token.position, "Expected a filter.")),
+        }
+    }
+}
+
+fn word_term(word : &str, position : usize) -> Result<Expression, QueryError> {
+    if word == "done" {
+        Ok(Expression::Done)
+    } else if let Some(project) = word.strip_prefix('+') {
+        match project {
+            "" => Err(QueryError::new(position, "Expected a project name after '+'.")),
+            project => Ok(Expression::Project(String::from(project))),
+        }
+    } else if let Some(context) = word.strip_prefix('@') {
+        match context {
+            "" => Err(QueryError::new(position, "Expected a context name after '@'.")),
+            context => Ok(Expression::Context(String::from(context))),
+        }
+    } else {
+        Ok(Expression::Text(String::from(word)))
+    }
+}
+
+fn comparison(key : &str, key_position : usize, operator : Operator, value : &str, value_position : usize) -> Result<Expression, QueryError> {
+    let date = || DateValue::parse(value)
+        .ok_or_else(|| QueryError::new(value_position, "Expected a date like 2018-11-24, today+3d or fri."));
+    match key {
+        "done" | "text" => Err(QueryError::new(key_position, &format!("'{}' can't be compared.", key))),
+        _ if key.starts_with('+') || key.starts_with('@') => Err(QueryError::new(key_position, "Projects and contexts can't be compared.")),
+        "pri" | "priority" => {
+            let priority = match value {
+                "none" => None,
+                _ if value.len() == 1 && value.chars().all(|character| character.is_ascii_alphabetic()) => value.to_uppercase().chars().next(),
+                _ => return Err(QueryError::new(value_position, "Expected a priority from A to Z or 'none'.")),
+            };
+            Ok(Expression::Priority(operator, priority))
+        },
+        "section" if operator.is_ordering() => Err(QueryError::new(key_position, "Sections can only be compared with ':' or '!='.")),
+        "section" => Ok(Expression::Section(operator, String::from(value))),
+        "created" => Ok(Expression::Date(DateField::Created, operator, date()?)),
+        "completed" => Ok(Expression::Date(DateField::Completed, operator, date()?)),
+        "due" | "t" => Ok(Expression::Date(DateField::Option(String::from(key)), operator, date()?)),
+        //words like `fri` are only dates for known date fields, other options need ISO dates
+        _ => match DateValue::parse(value) {
+            Some(date @ DateValue::Absolute(_)) => Ok(Expression::Date(DateField::Option(String::from(key)), operator, date)),
+            _ if operator.is_ordering() && value.parse::<f64>().is_err() =>
+                Err(QueryError::new(value_position, "Only numbers and dates can be compared with <, <=, > and >=.")),
+            _ => Ok(Expression::Option(String::from(key), operator, String::from(value))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use query::*;
+
+    fn today() -> Date<Utc> {
+        Utc.ymd(2018, 11, 24)
+    }
+
+    fn matching(query : &str, tasks : &[&str]) -> Vec<String> {
+        let predicate = Query::parse(query).expect("Must parse.").compile(today());
+        tasks.iter().map(|line| Task::new(line)).filter(|task| predicate(task)).map(|task| task.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_boolean_structure_with_precedence() {
+        let query = Query::parse("+a or @b and not done").expect("Must parse.");
+
+        assert_eq!(*query.expression(), Expression::Or(
+            Box::new(Expression::Project(String::from("a"))),
+            Box::new(Expression::And(
+                Box::new(Expression::Context(String::from("b"))),
+                Box::new(Expression::Not(Box::new(Expression::Done)))))));
+    }
+
+    #[test]
+    fn parses_comparisons() {
+        assert_eq!(*Query::parse("pri>=B").expect("Must parse.").expression(), Expression::Priority(Operator::GreaterOrEqual, Some('B')));
+        assert_eq!(*Query::parse("due <= today+3d").expect("Must parse.").expression(),
+            Expression::Date(DateField::Option(String::from("due")), Operator::LessOrEqual, DateValue::Relative(3, DateUnit::Day)));
+        assert_eq!(*Query::parse("owner:max").expect("Must parse.").expression(),
+            Expression::Option(String::from("owner"), Operator::Equal, String::from("max")));
+        assert_eq!(*Query::parse("owner!=\"max\"").expect("Must parse.").expression(),
+            Expression::Option(String::from("owner"), Operator::NotEqual, String::from("max")));
+    }
+
+    #[test]
+    fn filters_the_example_query() {
+        let tasks = [
+            "(A) Fix parser +rustodo @office owner:max",
+            "(C) Write docs +rustodo @office owner:max due:2018-11-26",
+            "(C) Write more docs +rustodo @office owner:max due:2018-12-26",
+            "x (A) Release +rustodo @office owner:max",
+            "(A) Fix parser +rustodo @home owner:max",
+            "(A) Fix parser +rustodo @office owner:thomas",
+        ];
+
+        assert_eq!(matching("+rustodo and @office and not done and (pri>=B or due<=today+3d) and owner:max", &tasks),
+            vec!["(A) Fix parser +rustodo @office owner:max", "(C) Write docs +rustodo @office owner:max due:2018-11-26"]);
+    }
+
+    #[test]
+    fn priorities_compare_from_highest_to_lowest() {
+        let tasks = ["(A) First", "(B) Second", "(C) Third", "No priority"];
+
+        assert_eq!(matching("pri>=B", &tasks), vec!["(A) First", "(B) Second"]);
+        assert_eq!(matching("pri<B", &tasks), vec!["(C) Third", "No priority"]);
+        assert_eq!(matching("pri:none", &tasks), vec!["No priority"]);
+
+        let mut task = Task::new("Set by hand");
+        task.priority = Some('a');
+        assert!(!Query::parse("pri>=Z").expect("Must parse.").matches(&task, today()));
+    }
+
+    #[test]
+    fn dates_and_numbers_can_be_compared() {
+        let tasks = ["2018-11-01 Old estimate:3", "2018-11-20 New estimate:12", "No date"];
+
+        assert_eq!(matching("created<today-1w", &tasks), vec!["2018-11-01 Old estimate:3"]);
+        assert_eq!(matching("created>=2018-11-20", &tasks), vec!["2018-11-20 New estimate:12"]);
+        assert_eq!(matching("estimate>5", &tasks), vec!["2018-11-20 New estimate:12"]);
+
+        let tasks = ["Soon due:2018-11-25", "Later due:fri", "Even later due:eoy", "Never status:fri"];
+        assert_eq!(matching("due<=tomorrow", &tasks), vec!["Soon due:2018-11-25"]);
+        assert_eq!(matching("due<=fri", &tasks), vec!["Soon due:2018-11-25", "Later due:fri"]);
+        assert_eq!(matching("status:fri", &tasks), vec!["Never status:fri"]);
+    }
+
+    #[test]
+    fn sections_are_matched_by_title() {
+        let query = Query::parse("section:\"Sprint 42\" and not done").expect("Must parse.");
+        let task = Task::new("Write report");
+
+        assert_eq!(*Query::parse("section!=Inbox").expect("Must parse.").expression(), Expression::Section(Operator::NotEqual, String::from("Inbox")));
+        assert!(query.matches_in_section(&task, Some("Sprint 42"), today()));
+        assert!(!query.matches_in_section(&task, Some("Sprint 43"), today()));
+        assert!(!query.matches(&task, today()));
+        assert!(Query::parse("section!=Inbox").expect("Must parse.").matches(&task, today()));
+        assert_eq!(Query::parse("section>=Sprint").expect_err("Must fail.").position, 0);
+    }
+
+    #[test]
+    fn words_search_the_description() {
+        let tasks = ["Pay the Invoice", "Call mom"];
+
+        assert_eq!(matching("invoice", &tasks), vec!["Pay the Invoice"]);
+        assert_eq!(matching("\"the invoice\"", &tasks), vec!["Pay the Invoice"]);
+    }
+
+    #[test]
+    fn errors_point_at_the_problem() {
+        let error = Query::parse("+a and (pri>=B or due<=soon)").expect_err("Must fail.");
+        assert_eq!(error.position, 23);
+        assert_eq!(error.highlight("+a and (pri>=B or due<=soon)"),
+            "+a and (pri>=B or due<=soon)\n                       ^ Expected a date like 2018-11-24, today+3d or fri.");
+
+        assert_eq!(Query::parse("+a and (@b").expect_err("Must fail.").position, 7);
+        assert_eq!(Query::parse("+a and").expect_err("Must fail.").position, 6);
+        assert_eq!(Query::parse("+a @b").expect_err("Must fail.").position, 3);
+        assert_eq!(Query::parse("pri>=9").expect_err("Must fail.").position, 5);
+        assert_eq!(Query::parse("owner>max").expect_err("Must fail.").position, 6);
+        assert_eq!(Query::parse("done:yes").expect_err("Must fail.").position, 0);
+        assert_eq!(Query::parse("\"open").expect_err("Must fail.").position, 0);
+        assert_eq!(Query::parse("+a)").expect_err("Must fail.").position, 2);
+    }
+}
@@ -0,0 +1,85 @@
+use std::collections::BTreeMap;
+use task::Task;
+
+/// A task flattened for scripts, with its line number and its parsed fields.
+/// Dates are ISO dates, `text` is the line as it is in the file.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TaskRecord {
+    pub line: usize,
+    pub completed: bool,
+    pub priority: Option<char>,
+    pub completed_at: Option<String>,
+    pub created_at: Option<String>,
+    pub projects: Vec<String>,
+    pub contexts: Vec<String>,
+    pub options: BTreeMap<String, String>,
+    pub text: String,
+}
+
+impl TaskRecord {
+    pub fn new(line : usize, task : &Task) -> TaskRecord {
+        TaskRecord {
+            line,
+            completed: task.completed,
+            priority: task.priority,
+            completed_at: task.completed_at().map(|date| date.format("%F").to_string()),
+            created_at: task.created_at().map(|date| date.format("%F").to_string()),
+            projects: task.projects(),
+            contexts: task.contexts(),
+            options: task.options().into_iter().collect(),
+            text: task.to_string(),
+        }
+    }
+
+    /// One line of tab separated fields, empty if a task doesn't have them:
+    ///
+    /// 1. the line number
+    /// 2. `x` for completed tasks
+    /// 3. the priority
+    /// 4. the completion date
+    /// 5. the creation date
+    /// 6. the projects, separated by spaces
+    /// 7. the contexts, separated by spaces
+    /// 8. the options as `key:value`, separated by spaces and sorted by key
+    /// 9. the text, which is last as it may contain tabs
+    pub fn porcelain(&self) -> String {
+        let fields = [
+            self.line.to_string(),
+            String::from(if self.completed { "x" } else { "" }),
+            self.priority.map(String::from).unwrap_or_default(),
+            self.completed_at.clone().unwrap_or_default(),
+            self.created_at.clone().unwrap_or_default(),
+            self.projects.join(" "),
+            self.contexts.join(" "),
+            self.options.iter().map(|(key, value)| format!("{}:{}", key, value)).collect::<Vec<String>>().join(" "),
+            self.text.clone(),
+        ];
+        fields.join("\t")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use record::*;
+    use serde_json;
+
+    #[test]
+    fn records_serialize_to_json_and_porcelain() {
+        let record = TaskRecord::new(3, &Task::new("x 2018-11-24 2018-11-20 Pay rent +home @bank due:2018-11-30 owner:max"));
+
+        assert_eq!(serde_json::to_value(&record).expect("Must serialize."), serde_json::json!({
+            "line": 3,
+            "completed": true,
+            "priority": null,
+            "completed_at": "2018-11-24",
+            "created_at": "2018-11-20",
+            "projects": ["home"],
+            "contexts": ["bank"],
+            "options": { "due": "2018-11-30", "owner": "max" },
+            "text": "x 2018-11-24 2018-11-20 Pay rent +home @bank due:2018-11-30 owner:max",
+        }));
+        assert_eq!(record.porcelain(),
+            "3\tx\t\t2018-11-24\t2018-11-20\thome\tbank\tdue:2018-11-30 owner:max\tx 2018-11-24 2018-11-20 Pay rent +home @bank due:2018-11-30 owner:max");
+        assert_eq!(TaskRecord::new(1, &Task::new("(A) Call mom")).porcelain(), "1\t\tA\t\t\t\t\t\t(A) Call mom");
+    }
+}
@@ -0,0 +1,276 @@
+use chrono::prelude::*;
+use std::collections::BTreeMap;
+use std::env;
+use std::io;
+use std::io::IsTerminal;
+use config::Variables;
+use description_component::DescriptionComponent;
+use parsers::*;
+use task::Task;
+
+const RESET : &str = "\x1b[0m";
+
+/// The colors of todo.sh, e.g. `YELLOW` or `LIGHT_BLUE`.
+const COLORS : [(&str, &str); 17] = [
+    ("BLACK", "0;30"),
+    ("RED", "0;31"),
+    ("GREEN", "0;32"),
+    ("BROWN", "0;33"),
+    ("BLUE", "0;34"),
+    ("PURPLE", "0;35"),
+    ("CYAN", "0;36"),
+    ("LIGHT_GREY", "0;37"),
+    ("DARK_GREY", "1;30"),
+    ("LIGHT_RED", "1;31"),
+    ("LIGHT_GREEN", "1;32"),
+    ("YELLOW", "1;33"),
+    ("LIGHT_BLUE", "1;34"),
+    ("LIGHT_PURPLE", "1;35"),
+    ("LIGHT_CYAN", "1;36"),
+    ("WHITE", "1;37"),
+    ("DEFAULT", "0"),
+];
+
+/// ANSI escape sequences for the parts of a task, `None` leaves a part uncolored.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub priorities: BTreeMap<char, String>,
+    /// For priorities without a color of their own.
+    pub other_priority: Option<String>,
+    pub done: Option<String>,
+    pub project: Option<String>,
+    pub context: Option<String>,
+    pub key_value: Option<String>,
+    pub overdue: Option<String>,
+    pub date: Option<String>,
+    pub number: Option<String>,
+}
+
+impl Default for Theme {
+    /// The colors of todo.sh, plus colors for tags and overdue tasks.
+    fn default() -> Theme {
+        Theme {
+            priorities: [('A', "YELLOW"), ('B', "GREEN"), ('C', "LIGHT_BLUE")].iter()
+                .filter_map(|&(priority, color)| Some((priority, parse_color(color)?)))
+                .collect(),
+            other_priority: parse_color("WHITE"),
+            done: parse_color("LIGHT_GREY"),
+            project: parse_color("PURPLE"),
+            context: parse_color("CYAN"),
+            key_value: parse_color("BROWN"),
+            overdue: parse_color("LIGHT_RED"),
+            date: None,
+            number: None,
+        }
+    }
+}
+
+impl Theme {
+    /// The default theme with the colors of the todo.sh variables `PRI_A` to `PRI_Z`,
+    /// `PRI_X`, `COLOR_DONE`, `COLOR_PROJECT`, `COLOR_CONTEXT`, `COLOR_META`, `COLOR_DATE`
+    /// and `COLOR_NUMBER`, and of `COLOR_OVERDUE`. Values are escape sequences like
+    /// `\033[0;33m` or color names like `yellow`, an empty value turns a color off.
+    pub fn from_variables(variables : &Variables) -> Theme {
+        let mut theme = Theme::default();
+        for (name, value) in variables {
+            let color = parse_color(value);
+            if color.is_none() && !value.is_empty() {
+                continue;
+            }
+            match name.as_str() {
+                "PRI_X" => theme.other_priority = color,
+                "COLOR_DONE" => theme.done = color,
+                "COLOR_PROJECT" => theme.project = color,
+                "COLOR_CONTEXT" => theme.context = color,
+                "COLOR_META" => theme.key_value = color,
+                "COLOR_OVERDUE" => theme.overdue = color,
+                "COLOR_DATE" => theme.date = color,
+                "COLOR_NUMBER" => theme.number = color,
+                name => match name.strip_prefix("PRI_").map(|priority| priority.chars().collect::<Vec<char>>()) {
+                    Some(ref priority) if priority.len() == 1 && priority[0].is_ascii_uppercase() => {
+                        match color {
+                            Some(color) => theme.priorities.insert(priority[0], color),
+                            None => theme.priorities.remove(&priority[0]),
+                        };
+                    },
+                    _ => {},
+                },
+            }
+        }
+        theme
+    }
+
+    /// The variables todo.sh exports for add-ons: the color names like `YELLOW` and the
+    /// colors of the theme like `PRI_A` and `COLOR_DONE`, written as `\\033[1;33m`.
+    /// Colors the theme leaves out are empty.
+    pub fn to_variables(&self) -> Variables {
+        let escape = |color : Option<&String>| color.map(|color| color.replace('\x1b', "\\\\033")).unwrap_or_default();
+        let mut variables = COLORS.iter()
+            .map(|&(name, code)| (String::from(name), format!("\\\\033[{}m", code)))
+            .collect::<Variables>();
+        for priority in 'A'..='Z' {
+            variables.insert(format!("PRI_{}", priority), escape(self.priorities.get(&priority)));
+        }
+        let colors = [
+            ("PRI_X", &self.other_priority), ("COLOR_DONE", &self.done), ("COLOR_PROJECT", &self.project),
+            ("COLOR_CONTEXT", &self.context), ("COLOR_META", &self.key_value), ("COLOR_OVERDUE", &self.overdue),
+            ("COLOR_DATE", &self.date), ("COLOR_NUMBER", &self.number),
+        ];
+        for &(name, color) in colors.iter() {
+            variables.insert(String::from(name), escape(color.as_ref()));
+        }
+        variables
+    }
+
+    fn priority(&self, priority : char) -> Option<&String> {
+        self.priorities.get(&priority).or(self.other_priority.as_ref())
+    }
+}
+
+/// Parses an escape sequence like `\033[0;33m` or `\\033[1;33m` as written in todo.sh
+/// configs, or a color name of todo.sh ignoring case, e.g. `light_blue` or `light blue`.
+pub fn parse_color(value : &str) -> Option<String> {
+    let value = value.trim();
+    for prefix in &["\\\\033", "\\033", "\\e", "\\x1b", "\x1b"] {
+        if let Some(sequence) = value.strip_prefix(prefix) {
+            return Some(format!("\x1b{}", sequence));
+        }
+    }
+    let name = value.to_uppercase().replace([' ', '-'], "_");
+    COLORS.iter()
+        .find(|&&(color, _)| color == name)
+        .map(|&(_, code)| format!("\x1b[{}m", code))
+}
+
+/// Renders tasks as ANSI colored text. The color of the priority is the color of the
+/// whole line, projects, contexts, options and dates get their own colors. Completed
+/// tasks are in the done color, options with a due date before today in the overdue color.
+#[derive(Debug, Clone)]
+pub struct Renderer {
+    pub theme: Theme,
+    pub colored: bool,
+}
+
+impl Renderer {
+    pub fn new(theme : Theme, colored : bool) -> Renderer {
+        Renderer { theme, colored }
+    }
+
+    /// Colors only if standard output is a terminal and `NO_COLOR` isn't set.
+    pub fn for_stdout(theme : Theme) -> Renderer {
+        let colored = env::var_os("NO_COLOR").is_none_or(|value| value.is_empty()) && io::stdout().is_terminal();
+        Renderer::new(theme, colored)
+    }
+
+    pub fn render(&self, task : &Task, today : Date<Utc>) -> String {
+        if !self.colored {
+            return task.to_string();
+        }
+        if task.completed {
+            return self.paint(&task.to_string(), self.theme.done.as_ref(), None);
+        }
+
+        let line_color = task.priority.and_then(|priority| self.theme.priority(priority));
+        let mut output = String::new();
+        if let Some(priority) = task.priority {
+            output.push_str(&format!("({}) ", priority));
+        }
+        if let Some(created_at) = task.created_at() {
+            output.push_str(&self.paint(&created_at.format("%F").to_string(), self.theme.date.as_ref(), line_color));
+            output.push(' ');
+        }
+        for component in task.description_components() {
+            let color = match component {
+                DescriptionComponent::Text(_) => None,
+                DescriptionComponent::Project(_) => self.theme.project.as_ref(),
+                DescriptionComponent::Context(_) => self.theme.context.as_ref(),
+                DescriptionComponent::KeyValue(key, value) if key == "due" && is_before(value, today) => self.theme.overdue.as_ref(),
+                DescriptionComponent::KeyValue(..) => self.theme.key_value.as_ref(),
+            };
+            output.push_str(&self.paint(&component.to_string(), color, line_color));
+        }
+        match line_color {
+            Some(line_color) => format!("{}{}{}", line_color, output, RESET),
+            None => output,
+        }
+    }
+
+    /// A task with its line number in front like in `todo.sh ls`, padded with zeros to `width`.
+    pub fn render_numbered(&self, line : usize, width : usize, task : &Task, today : Date<Utc>) -> String {
+        let number = format!("{:0width$}", line, width = width);
+        let number = if self.colored { self.paint(&number, self.theme.number.as_ref(), None) } else { number };
+        format!("{} {}", number, self.render(task, today))
+    }
+
+    //colors a part and switches back to the color of the line afterwards
+    fn paint(&self, text : &str, color : Option<&String>, line_color : Option<&String>) -> String {
+        match color {
+            Some(color) => format!("{}{}{}{}", color, text, RESET, line_color.map(String::as_str).unwrap_or("")),
+            None => String::from(text),
+        }
+    }
+}
+
+fn is_before(value : &str, today : Date<Utc>) -> bool {
+    match DateValue::parse(value) {
+        Some(date) => date.resolve(today) < today,
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use render::*;
+
+    fn renderer() -> Renderer {
+        let variables = [("PRI_A", "\\\\033[1;33m"), ("COLOR_PROJECT", "blue"), ("COLOR_CONTEXT", ""), ("COLOR_META", "light-grey"), ("PRI_B", "nonsense")]
+            .iter()
+            .map(|&(name, value)| (String::from(name), String::from(value)))
+            .collect::<Variables>();
+        Renderer::new(Theme::from_variables(&variables), true)
+    }
+
+    #[test]
+    fn parses_escape_sequences_and_names() {
+        assert_eq!(parse_color("\\033[0;33m"), Some(String::from("\x1b[0;33m")));
+        assert_eq!(parse_color("\\\\033[0;33m"), Some(String::from("\x1b[0;33m")));
+        assert_eq!(parse_color("Light Blue"), Some(String::from("\x1b[1;34m")));
+        assert_eq!(parse_color("nonsense"), None);
+    }
+
+    #[test]
+    fn variables_round_trip() {
+        let theme = renderer().theme;
+        let variables = theme.to_variables();
+
+        assert_eq!(variables["YELLOW"], "\\\\033[1;33m");
+        assert_eq!(variables["PRI_D"], "");
+        assert_eq!(Theme::from_variables(&variables), theme);
+    }
+
+    #[test]
+    fn colors_components_and_restores_the_line_color() {
+        let task = Task::new("(A) Pay rent +home @desk due:2018-11-30");
+
+        assert_eq!(renderer().render(&task, Utc.ymd(2018, 11, 24)),
+            "\x1b[1;33m(A) Pay rent \x1b[0;34m+home\x1b[0m\x1b[1;33m @desk \x1b[0;37mdue:2018-11-30\x1b[0m\x1b[1;33m\x1b[0m");
+        assert_eq!(renderer().render(&task, Utc.ymd(2018, 12, 1)),
+            "\x1b[1;33m(A) Pay rent \x1b[0;34m+home\x1b[0m\x1b[1;33m @desk \x1b[1;31mdue:2018-11-30\x1b[0m\x1b[1;33m\x1b[0m");
+    }
+
+    #[test]
+    fn completed_tasks_are_in_the_done_color() {
+        let task = Task::new("x 2018-11-24 Pay rent +home");
+
+        assert_eq!(renderer().render(&task, Utc.ymd(2018, 11, 24)), "\x1b[0;37mx 2018-11-24 Pay rent +home\x1b[0m");
+        assert_eq!(renderer().render(&Task::new("(B) Call mom"), Utc.ymd(2018, 11, 24)), "\x1b[0;32m(B) Call mom\x1b[0m");
+    }
+
+    #[test]
+    fn plain_renderers_render_the_task() {
+        let renderer = Renderer::new(Theme::default(), false);
+        let task = Task::new("(A) Pay rent +home");
+
+        assert_eq!(renderer.render_numbered(3, 2, &task, Utc.ymd(2018, 11, 24)), "03 (A) Pay rent +home");
+    }
+}
@@ -0,0 +1,198 @@
+use std::ops::Range;
+use description_component::DescriptionComponent;
+use task::Task;
+
+/// How much a match counts depending on the component it is found in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SearchWeights {
+    pub text: f64,
+    pub project: f64,
+    pub context: f64,
+}
+
+impl Default for SearchWeights {
+    fn default() -> SearchWeights {
+        SearchWeights {
+            text: 1.0,
+            project: 1.5,
+            context: 1.5,
+        }
+    }
+}
+
+/// A matched part of a description. `range` is a byte range in `Task::description()`
+/// that lies inside the span of the component with the index `component`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchMatch {
+    pub component: usize,
+    pub range: Range<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SearchResult<'a, T> {
+    pub handle: T,
+    pub task: &'a Task,
+    pub score: f64,
+    pub matches: Vec<SearchMatch>,
+}
+
+/// Ranks tasks by how well their descriptions match the words of the query, best match first.
+///
+/// Every word has to match the text, a project or a context of a task, either as
+/// a substring or fuzzily with its letters in order, e.g. `invc` matches `invoice`.
+/// Substrings score higher than fuzzy matches, matches at the start of a word
+/// score higher than others. Like `group_by`, tasks come with a handle like their line number.
+pub fn search<'a, T, I>(tasks : I, query : &str, weights : &SearchWeights) -> Vec<SearchResult<'a, T>>
+    where I: IntoIterator<Item = (T, &'a Task)> {
+    let terms = query.split_whitespace().map(lowercase_chars).collect::<Vec<Vec<char>>>();
+    let mut results = tasks.into_iter()
+        .filter_map(|(handle, task)| {
+            let (score, matches) = score_task(task, &terms, weights)?;
+            Some(SearchResult { handle, task, score, matches })
+        })
+        .collect::<Vec<SearchResult<T>>>();
+    //weights are public, total_cmp keeps NaN scores from a NaN weight from panicking
+    results.sort_by(|left, right| right.score.total_cmp(&left.score));
+    results
+}
+
+fn score_task(task : &Task, terms : &[Vec<char>], weights : &SearchWeights) -> Option<(f64, Vec<SearchMatch>)> {
+    let spans = task.description_component_spans();
+    let mut total_score = 0.0;
+    let mut matches = Vec::new();
+    for term in terms {
+        let mut best : Option<(f64, usize, Vec<Range<usize>>)> = None;
+        for (index, component) in task.description_components().iter().enumerate() {
+            //the searchable part of the component and its offset in the description
+            let (text, offset, weight) = match component {
+                DescriptionComponent::Text(text) => (text.as_str(), spans[index].start, weights.text),
+                DescriptionComponent::Project(project) => (project.as_str(), spans[index].start + 1, weights.project),
+                DescriptionComponent::Context(context) => (context.as_str(), spans[index].start + 1, weights.context),
+                DescriptionComponent::KeyValue(..) => continue,
+            };
+            if let Some((score, ranges)) = match_term(text, term) {
+                let score = score * weight;
+                if best.as_ref().is_none_or(|best| score > best.0) {
+                    best = Some((score, index, ranges.into_iter().map(|range| range.start + offset..range.end + offset).collect()));
+                }
+            }
+        }
+        let (score, component, ranges) = best?;
+        total_score += score;
+        matches.extend(ranges.into_iter().map(|range| SearchMatch { component, range }));
+    }
+    matches.sort_by_key(|search_match| search_match.range.start);
+    Some((total_score, matches))
+}
+
+/// Matches a lowercase term against a text and returns a score and the matched byte ranges.
+fn match_term(text : &str, term : &[char]) -> Option<(f64, Vec<Range<usize>>)> {
+    let characters = text.char_indices()
+        .map(|(index, character)| (index, lowercase(character), index + character.len_utf8()))
+        .collect::<Vec<(usize, char, usize)>>();
+    let is_word_start = |position : usize| position == 0 || !characters[position - 1].1.is_alphanumeric();
+    if term.is_empty() || term.len() > characters.len() {
+        return None;
+    }
+
+    //substrings, preferably at the start of a word
+    let mut substring : Option<usize> = None;
+    for start in 0..=characters.len() - term.len() {
+        if characters[start..start + term.len()].iter().map(|&(_, character, _)| character).eq(term.iter().cloned()) {
+            if is_word_start(start) {
+                substring = Some(start);
+                break;
+            }
+            substring = substring.or(Some(start));
+        }
+    }
+    if let Some(start) = substring {
+        let score = if is_word_start(start) { 1.0 } else { 0.8 };
+        let range = characters[start].0..characters[start + term.len() - 1].2;
+        return Some((score, vec![range]));
+    }
+
+    //fuzzy matches with all characters in order, the closer together the better
+    let mut positions = Vec::with_capacity(term.len());
+    let mut remaining = term.iter().peekable();
+    for (position, &(_, character, _)) in characters.iter().enumerate() {
+        if remaining.peek() == Some(&&character) {
+            positions.push(position);
+            remaining.next();
+        }
+    }
+    if remaining.peek().is_some() {
+        return None;
+    }
+    let spread = positions[positions.len() - 1] - positions[0] + 1;
+    let score = 0.6 * term.len() as f64 / spread as f64;
+
+    let mut ranges : Vec<Range<usize>> = Vec::new();
+    for position in positions {
+        let (start, _, end) = characters[position];
+        match ranges.last_mut() {
+            Some(ref mut range) if range.end == start => range.end = end,
+            _ => ranges.push(start..end),
+        }
+    }
+    Some((score, ranges))
+}
+
+fn lowercase(character : char) -> char {
+    character.to_lowercase().next().unwrap_or(character)
+}
+
+fn lowercase_chars(term : &str) -> Vec<char> {
+    term.chars().map(lowercase).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use search::*;
+    use task_list::TaskList;
+
+    fn ranked_lines(list : &TaskList, query : &str) -> Vec<usize> {
+        search(list.tasks(), query, &SearchWeights::default()).iter().map(|result| result.handle).collect()
+    }
+
+    #[test]
+    fn substrings_rank_before_fuzzy_matches() {
+        let list = TaskList::parse("todo.txt", "Send the inventory count\nPay the invoice for Bob\nCall mom\nreinvoice Alice\n");
+
+        assert_eq!(ranked_lines(&list, "invoice"), vec![2, 4]);
+        assert_eq!(ranked_lines(&list, "invc"), vec![2, 4, 1]);
+        assert!(ranked_lines(&list, "xyz").is_empty());
+    }
+
+    #[test]
+    fn all_words_have_to_match() {
+        let list = TaskList::parse("todo.txt", "Pay the invoice for Bob\nPay the invoice for Alice\n");
+
+        assert_eq!(ranked_lines(&list, "invoice ALICE"), vec![2]);
+    }
+
+    #[test]
+    fn projects_and_contexts_use_their_weights() {
+        let list = TaskList::parse("todo.txt", "Talk about the garden\nWater plants +garden\n");
+        assert_eq!(ranked_lines(&list, "garden"), vec![2, 1]);
+
+        let weights = SearchWeights { text: 2.0, project: 1.0, context: 1.0 };
+        let lines = search(list.tasks(), "garden", &weights).iter().map(|result| result.handle).collect::<Vec<usize>>();
+        assert_eq!(lines, vec![1, 2]);
+
+        let weights = SearchWeights { project: f64::NAN, ..SearchWeights::default() };
+        assert_eq!(search(list.tasks(), "garden", &weights).len(), 2);
+    }
+
+    #[test]
+    fn matches_point_into_the_description() {
+        let task = Task::new("(A) Pay the invoice +accounting @office");
+        let results = search(vec![(1, &task)], "invoice acct", &SearchWeights::default());
+        let description = task.description();
+
+        let ranges = results[0].matches.iter().map(|search_match| &description[search_match.range.clone()]).collect::<Vec<&str>>();
+        assert_eq!(ranges, vec!["invoice", "acc", "t"]);
+        assert_eq!(results[0].matches[0].component, 0);
+        assert_eq!(results[0].matches[1].component, 1);
+    }
+}
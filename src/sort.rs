@@ -0,0 +1,213 @@
+use chrono::prelude::*;
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Error;
+use parsers::*;
+use task::Task;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortKey {
+    Priority,
+    Due,
+    Threshold,
+    Created,
+    Completed,
+    Done,
+    Text,
+    Project,
+    Context,
+    /// The whole line compared like `sort -f` in the C locale, which is how `todo.sh ls` orders tasks.
+    TodoSh,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SortField {
+    pub key: SortKey,
+    pub descending: bool,
+}
+
+/// How to sort tasks, parsed from strings like `pri,-due,created,text`.
+///
+/// Keys are compared in order, a `-` reverses the direction of a key. Tasks
+/// without a value for a key, e.g. without priority or due date, always come last.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SortSpec {
+    fields: Vec<SortField>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnknownSortKey(pub String);
+
+impl Display for UnknownSortKey {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
+        write!(formatter, "Unknown sort key '{}'.", self.0)
+    }
+}
+
+impl Default for SortSpec {
+    /// Priority, then due date, then creation date, then text.
+    fn default() -> SortSpec {
+        SortSpec::parse("pri,due,created,text").expect("The default sort spec is invalid.")
+    }
+}
+
+impl SortSpec {
+    pub fn new(fields : Vec<SortField>) -> SortSpec {
+        SortSpec { fields }
+    }
+
+    /// The ordering of `todo.sh ls`. Ties keep their order, so sort tasks in file order.
+    pub fn todo_sh() -> SortSpec {
+        SortSpec::new(vec![SortField { key: SortKey::TodoSh, descending: false }])
+    }
+
+    pub fn parse(input : &str) -> Result<SortSpec, UnknownSortKey> {
+        let mut fields = Vec::new();
+        for name in input.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+            let (descending, key_name) = match name.strip_prefix('-') {
+                Some(key_name) => (true, key_name),
+                None => (false, name.trim_start_matches('+')),
+            };
+            let key = match key_name {
+                "pri" | "priority" => SortKey::Priority,
+                "due" => SortKey::Due,
+                "t" | "threshold" => SortKey::Threshold,
+                "created" => SortKey::Created,
+                "completed" => SortKey::Completed,
+                "done" => SortKey::Done,
+                "text" | "description" => SortKey::Text,
+                "project" => SortKey::Project,
+                "context" => SortKey::Context,
+                "todo.sh" => SortKey::TodoSh,
+                _ => return Err(UnknownSortKey(String::from(key_name))),
+            };
+            fields.push(SortField { key, descending });
+        }
+        Ok(SortSpec { fields })
+    }
+
+    pub fn fields(&self) -> &[SortField] {
+        &self.fields
+    }
+
+    pub fn compare(&self, left : &Task, right : &Task) -> Ordering {
+        self.fields.iter()
+            .map(|field| compare_field(field, left, right))
+            .find(|ordering| *ordering != Ordering::Equal)
+            .unwrap_or(Ordering::Equal)
+    }
+
+    /// Sorts tasks in place, tasks that compare equal keep their order.
+    pub fn sort<T: Borrow<Task>>(&self, tasks : &mut [T]) {
+        tasks.sort_by(|left, right| self.compare(left.borrow(), right.borrow()))
+    }
+}
+
+fn compare_field(field : &SortField, left : &Task, right : &Task) -> Ordering {
+    let descending = field.descending;
+    match field.key {
+        SortKey::Priority => compare_present_first(left.priority, right.priority, descending),
+        SortKey::Due => compare_present_first(date_option(left, "due"), date_option(right, "due"), descending),
+        SortKey::Threshold => compare_present_first(date_option(left, "t"), date_option(right, "t"), descending),
+        SortKey::Created => compare_present_first(left.created_at(), right.created_at(), descending),
+        SortKey::Completed => compare_present_first(left.completed_at(), right.completed_at(), descending),
+        SortKey::Project => compare_present_first(first_lowercase(left.projects()), first_lowercase(right.projects()), descending),
+        SortKey::Context => compare_present_first(first_lowercase(left.contexts()), first_lowercase(right.contexts()), descending),
+        SortKey::Done => directed(left.completed.cmp(&right.completed), descending),
+        SortKey::Text => directed(left.description().to_lowercase().cmp(&right.description().to_lowercase()), descending),
+        SortKey::TodoSh => directed(left.to_string().to_ascii_uppercase().cmp(&right.to_string().to_ascii_uppercase()), descending),
+    }
+}
+
+fn directed(ordering : Ordering, descending : bool) -> Ordering {
+    if descending {
+        ordering.reverse()
+    } else {
+        ordering
+    }
+}
+
+//missing values come last in both directions
+fn compare_present_first<T: Ord>(left : Option<T>, right : Option<T>, descending : bool) -> Ordering {
+    match (left, right) {
+        (Some(ref left), Some(ref right)) if descending => right.cmp(left),
+        (Some(left), Some(right)) => left.cmp(&right),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+fn date_option(task : &Task, key : &str) -> Option<Date<Utc>> {
+    match DateParser::parse(task.options().get(key)?) {
+        Some(ref date_result) if date_result.remaining.is_empty() => Some(date_result.value),
+        _ => None,
+    }
+}
+
+fn first_lowercase(values : Vec<String>) -> Option<String> {
+    values.into_iter().next().map(|value| value.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use sort::*;
+
+    fn sorted(spec : &SortSpec, lines : &[&str]) -> Vec<String> {
+        let mut tasks = lines.iter().map(|line| Task::new(line)).collect::<Vec<Task>>();
+        spec.sort(&mut tasks);
+        tasks.iter().map(|task| task.to_string()).collect()
+    }
+
+    #[test]
+    fn parse_reads_keys_and_directions() {
+        let spec = SortSpec::parse("pri, -due,created,+text").expect("Must parse.");
+
+        assert_eq!(spec.fields(), &[
+            SortField { key: SortKey::Priority, descending: false },
+            SortField { key: SortKey::Due, descending: true },
+            SortField { key: SortKey::Created, descending: false },
+            SortField { key: SortKey::Text, descending: false },
+        ]);
+        assert_eq!(SortSpec::parse("pri,size"), Err(UnknownSortKey(String::from("size"))));
+    }
+
+    #[test]
+    fn default_sorts_by_priority_due_created_and_text() {
+        let lines = [
+            "No priority",
+            "(B) 2018-11-20 Second",
+            "(A) Third due:2018-12-01",
+            "(A) First due:2018-11-30",
+            "(B) 2018-11-19 Zebra",
+            "(B) 2018-11-19 apple",
+        ];
+
+        assert_eq!(sorted(&SortSpec::default(), &lines), vec![
+            "(A) First due:2018-11-30",
+            "(A) Third due:2018-12-01",
+            "(B) 2018-11-19 apple",
+            "(B) 2018-11-19 Zebra",
+            "(B) 2018-11-20 Second",
+            "No priority",
+        ]);
+    }
+
+    #[test]
+    fn missing_values_stay_last_when_descending() {
+        let lines = ["No priority", "(A) First", "(C) Third"];
+        assert_eq!(sorted(&SortSpec::parse("-pri").expect("Must parse."), &lines), vec!["(C) Third", "(A) First", "No priority"]);
+
+        let lines = ["No due", "Early due:2018-11-01", "Late due:2018-12-01"];
+        assert_eq!(sorted(&SortSpec::parse("-due").expect("Must parse."), &lines), vec!["Late due:2018-12-01", "Early due:2018-11-01", "No due"]);
+    }
+
+    #[test]
+    fn todo_sh_ordering_sorts_whole_lines_ignoring_case() {
+        let lines = ["x Done", "call mom", "(B) Beta", "Buy milk", "(A) Alpha", "Buy milk"];
+
+        assert_eq!(sorted(&SortSpec::todo_sh(), &lines), vec!["(A) Alpha", "(B) Beta", "Buy milk", "Buy milk", "call mom", "x Done"]);
+    }
+}
@@ -0,0 +1,193 @@
+use chrono::prelude::*;
+use chrono::Duration;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use group::iso_week_name;
+use task::Task;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct Counts {
+    pub open: usize,
+    pub closed: usize,
+}
+
+impl Counts {
+    fn count(&mut self, task : &Task) {
+        if task.completed {
+            self.closed += 1;
+        } else {
+            self.open += 1;
+        }
+    }
+}
+
+/// Ages and lead times in days.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Durations {
+    pub count: usize,
+    pub median: Option<f64>,
+    pub mean: Option<f64>,
+}
+
+impl Durations {
+    fn of(mut days : Vec<i64>) -> Durations {
+        days.sort();
+        let count = days.len();
+        let median = match count {
+            0 => None,
+            _ if count.is_multiple_of(2) => Some((days[count / 2 - 1] + days[count / 2]) as f64 / 2.0),
+            _ => Some(days[count / 2] as f64),
+        };
+        let mean = if count == 0 { None } else { Some(days.iter().sum::<i64>() as f64 / count as f64) };
+        Durations { count, median, mean }
+    }
+}
+
+/// Statistics over tasks, e.g. the tasks of a todo list and its done list together.
+///
+/// Ages and lead times only count tasks with the needed dates. The weekly histograms
+/// are keyed by ISO week like `2018-W47` and contain every week between the first and
+/// the last one, so weeks without activity show up with a count of 0.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Stats {
+    pub total: Counts,
+    pub projects: BTreeMap<String, Counts>,
+    pub contexts: BTreeMap<String, Counts>,
+    /// Days since the creation of open tasks.
+    pub open_age: Durations,
+    /// Days from creation to completion of closed tasks.
+    pub lead_time: Durations,
+    /// Closed tasks per week of their completion date.
+    pub completed_per_week: BTreeMap<String, usize>,
+    /// Tasks per week of their creation date.
+    pub created_per_week: BTreeMap<String, usize>,
+}
+
+pub fn stats<'a, I: IntoIterator<Item = &'a Task>>(tasks : I, today : Date<Utc>) -> Stats {
+    let mut total = Counts::default();
+    let mut projects : BTreeMap<String, Counts> = BTreeMap::new();
+    let mut contexts : BTreeMap<String, Counts> = BTreeMap::new();
+    let mut open_ages = Vec::new();
+    let mut lead_times = Vec::new();
+    let mut completed_dates = Vec::new();
+    let mut created_dates = Vec::new();
+
+    for task in tasks {
+        total.count(task);
+        //a task with +a +a counts once for a
+        for project in task.projects().into_iter().collect::<BTreeSet<String>>() {
+            projects.entry(project).or_default().count(task);
+        }
+        for context in task.contexts().into_iter().collect::<BTreeSet<String>>() {
+            contexts.entry(context).or_default().count(task);
+        }
+        match (task.completed, task.created_at(), task.completed_at()) {
+            (false, Some(created_at), _) => open_ages.push((today - created_at).num_days()),
+            (true, Some(created_at), Some(completed_at)) => lead_times.push((completed_at - created_at).num_days()),
+            _ => {},
+        }
+        if let Some(completed_at) = task.completed_at() {
+            completed_dates.push(completed_at);
+        }
+        if let Some(created_at) = task.created_at() {
+            created_dates.push(created_at);
+        }
+    }
+
+    Stats {
+        total,
+        projects,
+        contexts,
+        open_age: Durations::of(open_ages),
+        lead_time: Durations::of(lead_times),
+        completed_per_week: weekly_histogram(&completed_dates),
+        created_per_week: weekly_histogram(&created_dates),
+    }
+}
+
+fn weekly_histogram(dates : &[Date<Utc>]) -> BTreeMap<String, usize> {
+    let mut histogram = BTreeMap::new();
+    let (first, last) = match (dates.iter().min(), dates.iter().max()) {
+        (Some(&first), Some(&last)) => (first, last),
+        _ => return histogram,
+    };
+    let mut monday = week_start(first);
+    while monday <= last {
+        histogram.insert(iso_week_name(monday), 0);
+        monday = monday + Duration::weeks(1);
+    }
+    for &date in dates {
+        *histogram.entry(iso_week_name(date)).or_insert(0) += 1;
+    }
+    histogram
+}
+
+fn week_start(date : Date<Utc>) -> Date<Utc> {
+    date - Duration::days(i64::from(date.weekday().num_days_from_monday()))
+}
+
+#[cfg(test)]
+mod tests {
+    use stats::*;
+    use serde_json;
+    use task_list::TaskList;
+
+    fn fixture() -> Stats {
+        let todo = TaskList::parse("todo.txt", "2018-11-20 Write docs +rustodo @office\n2018-11-14 Call mom @phone\nNo date +rustodo\n");
+        let done = TaskList::parse("done.txt", "x 2018-11-05 2018-11-01 Fix bug +rustodo\nx 2018-11-22 2018-11-12 Release +rustodo @office\n");
+        stats(todo.tasks().chain(done.tasks()).map(|(_, task)| task), Utc.ymd(2018, 11, 24))
+    }
+
+    #[test]
+    fn counts_open_and_closed_tasks() {
+        let stats = fixture();
+
+        assert_eq!(stats.total, Counts { open: 3, closed: 2 });
+        assert_eq!(stats.projects["rustodo"], Counts { open: 2, closed: 2 });
+        assert_eq!(stats.contexts["office"], Counts { open: 1, closed: 1 });
+        assert_eq!(stats.contexts["phone"], Counts { open: 1, closed: 0 });
+
+        let repeated = Task::new("Fix bug +rustodo +rustodo @home @home");
+        let repeated = super::stats(vec![&repeated], Utc.ymd(2018, 11, 24));
+        assert_eq!(repeated.projects["rustodo"], Counts { open: 1, closed: 0 });
+        assert_eq!(repeated.contexts["home"], Counts { open: 1, closed: 0 });
+    }
+
+    #[test]
+    fn ages_and_lead_times_are_in_days() {
+        let stats = fixture();
+
+        assert_eq!(stats.open_age, Durations { count: 2, median: Some(7.0), mean: Some(7.0) });
+        assert_eq!(stats.lead_time, Durations { count: 2, median: Some(7.0), mean: Some(7.0) });
+        assert_eq!(Durations::of(vec![9, 1, 2]).median, Some(2.0));
+        assert_eq!(Durations::of(Vec::new()), Durations { count: 0, median: None, mean: None });
+    }
+
+    #[test]
+    fn weekly_histograms_include_empty_weeks() {
+        let stats = fixture();
+
+        let completed = stats.completed_per_week.iter().map(|(week, &count)| (week.as_str(), count)).collect::<Vec<(&str, usize)>>();
+        assert_eq!(completed, vec![("2018-W45", 1), ("2018-W46", 0), ("2018-W47", 1)]);
+        assert_eq!(stats.created_per_week.values().sum::<usize>(), 4);
+    }
+
+    #[test]
+    fn completions_without_a_creation_date_are_counted() {
+        let done = TaskList::parse("done.txt", "x 2018-11-22 Pay rent +home\n");
+        let stats = stats(done.tasks().map(|(_, task)| task), Utc.ymd(2018, 11, 24));
+
+        assert_eq!(stats.total, Counts { open: 0, closed: 1 });
+        assert_eq!(stats.completed_per_week.get("2018-W47"), Some(&1));
+        assert_eq!(stats.lead_time.count, 0);
+    }
+
+    #[test]
+    fn stats_can_be_serialized() {
+        let json = serde_json::to_value(fixture()).expect("Must serialize.");
+
+        assert_eq!(json["total"]["open"], 3);
+        assert_eq!(json["lead_time"]["median"], 7.0);
+        assert_eq!(json["completed_per_week"]["2018-W46"], 0);
+    }
+}
@@ -0,0 +1,191 @@
+use chrono::prelude::*;
+use std::io;
+use std::io::BufRead;
+use std::io::Write;
+use format::Format;
+use line::DEFAULT_COMMENT_PREFIX;
+use line::Line;
+use query::Query;
+use sort::SortSpec;
+
+//a line of a stream, lines that aren't UTF-8 are kept as bytes
+enum Entry {
+    Line(Vec<u8>, Line),
+    Raw(Vec<u8>),
+}
+
+impl Entry {
+    fn bytes(&self) -> &[u8] {
+        match self {
+            Entry::Line(bytes, _) | Entry::Raw(bytes) => bytes,
+        }
+    }
+
+    fn line(&self) -> Option<&Line> {
+        match self {
+            Entry::Line(_, line) => Some(line),
+            Entry::Raw(_) => None,
+        }
+    }
+}
+
+/// Reads a stream line by line and hands each line to `handle`, with its parsed
+/// form unless it isn't UTF-8. Line breaks aren't part of the line.
+fn read_entries<F>(input : &mut dyn BufRead, mut handle : F) -> io::Result<()>
+    where F: FnMut(Entry) -> io::Result<()> {
+    let mut bytes = Vec::new();
+    while input.read_until(b'\n', &mut bytes)? > 0 {
+        if bytes.last() == Some(&b'\n') {
+            bytes.pop();
+        }
+        let entry = match String::from_utf8(bytes.clone()) {
+            Ok(text) => {
+                let line = Line::parse(text.trim_end_matches('\r'), Some(DEFAULT_COMMENT_PREFIX));
+                Entry::Line(bytes.clone(), line)
+            },
+            Err(_) => Entry::Raw(bytes.clone()),
+        };
+        handle(entry)?;
+        bytes.clear();
+    }
+    Ok(())
+}
+
+fn write_bytes(output : &mut dyn Write, bytes : &[u8]) -> io::Result<()> {
+    output.write_all(bytes)?;
+    output.write_all(b"\n")
+}
+
+/// Copies the tasks matching a query from `input` to `output` as they are. Comments,
+/// blank lines and lines that aren't UTF-8 are copied too, comments also start the
+/// sections `section:` matches. Returns the number of matching tasks.
+pub fn filter_stream(input : &mut dyn BufRead, output : &mut dyn Write, query : &Query, today : Date<Utc>) -> io::Result<usize> {
+    let mut count = 0;
+    let mut section : Option<String> = None;
+    read_entries(input, |entry| {
+        match entry.line() {
+            Some(Line::Task(task)) if !query.matches_in_section(task, section.as_deref(), today) => return Ok(()),
+            Some(Line::Task(_)) => count += 1,
+            Some(line @ Line::Comment(_)) => section = line.comment_text(DEFAULT_COMMENT_PREFIX).map(String::from),
+            _ => {},
+        }
+        write_bytes(output, entry.bytes())
+    })?;
+    Ok(count)
+}
+
+/// Sorts the tasks of `input` into `output` without changing them. Like `Format::sort`,
+/// tasks are sorted within the blocks between other lines, which keep their place.
+/// Ties keep their order.
+pub fn sort_stream(input : &mut dyn BufRead, output : &mut dyn Write, sort : &SortSpec) -> io::Result<()> {
+    let mut block : Vec<Entry> = Vec::new();
+    let write_block = |output : &mut dyn Write, block : &mut Vec<Entry>| -> io::Result<()> {
+        block.sort_by(|left, right| match (left.line().and_then(Line::task), right.line().and_then(Line::task)) {
+            (Some(left), Some(right)) => sort.compare(left, right),
+            _ => unreachable!("Blocks only contain tasks."),
+        });
+        for entry in block.drain(..) {
+            write_bytes(output, entry.bytes())?;
+        }
+        Ok(())
+    };
+    read_entries(input, |entry| {
+        if entry.line().and_then(Line::task).is_some() {
+            block.push(entry);
+            return Ok(());
+        }
+        write_block(output, &mut block)?;
+        write_bytes(output, entry.bytes())
+    })?;
+    write_block(output, &mut block)
+}
+
+/// Writes the tasks of `input` in canonical form to `output`, see `Format`. Lines that
+/// aren't tasks are copied as they are. Returns the number of changed lines.
+pub fn format_stream(input : &mut dyn BufRead, output : &mut dyn Write, format : &Format, today : Date<Utc>) -> io::Result<usize> {
+    let mut changed = 0;
+    let mut block : Vec<(Vec<u8>, Line)> = Vec::new();
+    let mut write_block = |output : &mut dyn Write, block : &mut Vec<(Vec<u8>, Line)>| -> io::Result<()> {
+        let lines = block.iter().map(|(_, line)| line.clone()).collect::<Vec<Line>>();
+        //sorting may move tasks, so lines are compared at their positions
+        for ((bytes, _), line) in block.drain(..).zip(format.lines(&lines, today)) {
+            let line = line.to_string();
+            if line.as_bytes() != bytes.as_slice() {
+                changed += 1;
+            }
+            write_bytes(output, line.as_bytes())?;
+        }
+        Ok(())
+    };
+    //without sorting every line is a block of its own, so the stream isn't held back
+    read_entries(input, |entry| match entry {
+        Entry::Line(bytes, line) => {
+            block.push((bytes, line));
+            if format.sort.is_none() {
+                write_block(output, &mut block)?;
+            }
+            Ok(())
+        },
+        Entry::Raw(bytes) => {
+            write_block(output, &mut block)?;
+            write_bytes(output, &bytes)
+        },
+    })?;
+    write_block(output, &mut block)?;
+    Ok(changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use stream::*;
+
+    const INPUT : &[u8] = b"(B) Write docs due:2018-11-30\n# Home\nPay   rent +home\n\xff broken\n(C) Buy milk\n(A) Call mom +home\n\n";
+
+    fn output<F>(run : F) -> String
+        where F: FnOnce(&mut dyn BufRead, &mut dyn Write) -> io::Result<()> {
+        let mut output = Vec::new();
+        run(&mut &INPUT[..], &mut output).expect("Must process the stream.");
+        String::from_utf8_lossy(&output).into_owned()
+    }
+
+    #[test]
+    fn filter_keeps_other_lines() {
+        let query = Query::parse("+home").expect("Must parse.");
+
+        assert_eq!(output(|input, output| filter_stream(input, output, &query, Utc.ymd(2018, 11, 24)).map(|_| ())),
+            "# Home\nPay   rent +home\n\u{fffd} broken\n(A) Call mom +home\n\n");
+
+        let query = Query::parse("section:Home and pri:none").expect("Must parse.");
+        assert_eq!(output(|input, output| filter_stream(input, output, &query, Utc.ymd(2018, 11, 24)).map(|_| ())),
+            "# Home\nPay   rent +home\n\u{fffd} broken\n\n");
+    }
+
+    #[test]
+    fn sort_stays_within_blocks() {
+        let sort = SortSpec::parse("pri").expect("Must parse.");
+
+        assert_eq!(output(|input, output| sort_stream(input, output, &sort)),
+            "(B) Write docs due:2018-11-30\n# Home\nPay   rent +home\n\u{fffd} broken\n(A) Call mom +home\n(C) Buy milk\n\n");
+        let mut sorted = Vec::new();
+        sort_stream(&mut &b"Write docs\n(A) Call mom\n"[..], &mut sorted, &sort).expect("Must sort.");
+        assert_eq!(sorted, b"(A) Call mom\nWrite docs\n");
+    }
+
+    #[test]
+    fn format_rewrites_tasks_only() {
+        let mut changed = 0;
+        let formatted = output(|input, output| {
+            changed = format_stream(input, output, &Format::default(), Utc.ymd(2018, 11, 24))?;
+            Ok(())
+        });
+
+        assert_eq!(formatted, "(B) Write docs due:2018-11-30\n# Home\nPay rent +home\n\u{fffd} broken\n(C) Buy milk\n(A) Call mom +home\n\n");
+        assert_eq!(changed, 1);
+
+        let format = Format { sort: SortSpec::parse("pri").ok(), ..Format::default() };
+        let mut sorted = Vec::new();
+        let changed = format_stream(&mut &INPUT[..], &mut sorted, &format, Utc.ymd(2018, 11, 24)).expect("Must format.");
+        assert_eq!(String::from_utf8_lossy(&sorted), "(B) Write docs due:2018-11-30\n# Home\nPay rent +home\n\u{fffd} broken\n(A) Call mom +home\n(C) Buy milk\n\n");
+        assert_eq!(changed, 3);
+    }
+}
@@ -1,13 +1,18 @@
 use chrono::prelude::*;
 use std::collections::HashMap;
+use std::ops::Range;
 use std::fmt::Display;
 use std::fmt::Formatter;
 use std::fmt::Error;
 use description_component::DescriptionComponentsParser;
 use description_component::DescriptionComponent;
 use description_component::description_components_to_string;
+use description_component::description_component_spans;
 use parsers::*;
 
+/// The options holding dates, relative dates in them are resolved by `Task::normalize_dates`.
+pub const DATE_KEYS : [&str; 2] = ["due", "t"];
+
 #[derive(Debug, Clone)]
 pub struct Task {
     pub completed : bool,
@@ -20,12 +25,27 @@ pub struct Task {
 impl Task {
     pub fn new(input : &str) -> Task {
         let completed_result = CompletionMarkParser::parse(input).expect("If this doesn't parse, you have found a bug!");
+        let priority_result = PriorityParser::parse(completed_result.remaining).expect("If this doesn't parse, you have found a bug!");
+        let first_date_result = DateFieldParser::parse(priority_result.remaining).expect("If this doesn't parse, you have found a bug!");
+
+        //the first date of a completed task is the completion date, a second one is the creation date
+        let (completed_at, created_at, remaining) = match first_date_result.value {
+            Some(first_date) if completed_result.value => {
+                let second_date_result = DateFieldParser::parse(first_date_result.remaining).expect("If this doesn't parse, you have found a bug!");
+                match second_date_result.value {
+                    Some(second_date) => (Some(first_date), Some(second_date), second_date_result.remaining),
+                    None => (Some(first_date), None, first_date_result.remaining),
+                }
+            },
+            first_date => (None, first_date, first_date_result.remaining),
+        };
+
         Task {
             completed: completed_result.value,
-            priority: None,
-            completed_at: None,
-            created_at: None,
-            description: DescriptionComponentsParser::parse(completed_result.remaining)
+            priority: priority_result.value,
+            completed_at,
+            created_at,
+            description: DescriptionComponentsParser::parse(remaining)
                 .expect("Descriptions must be parseable. If not you have found a bug!")
                 .value,
         }
@@ -129,6 +149,44 @@ impl Task {
     pub fn description_components(&self) -> &Vec<DescriptionComponent> {
         &self.description
     }
+
+    /// The byte ranges of the description components in `description()`, in the same order.
+    pub fn description_component_spans(&self) -> Vec<Range<usize>> {
+        description_component_spans(&self.description)
+    }
+
+    /// Marks the task as completed on a date like `todo.sh do`: the priority is removed
+    /// and the completion date is set. The spec wants a creation date next to it, tasks
+    /// without one get the completion date, like `lint --fix` does.
+    pub fn complete(&mut self, date : Date<Utc>) {
+        self.completed = true;
+        self.priority = None;
+        if self.created_at.is_none() {
+            self.created_at = Some(date);
+        }
+        self.completed_at = Some(date);
+    }
+
+    /// Rewrites relative dates like `due:tomorrow` or `t:+2w` in the `DATE_KEYS` options
+    /// to ISO dates, resolved against `today`. Returns whether anything changed.
+    pub fn normalize_dates(&mut self, today : Date<Utc>) -> bool {
+        let mut changed = false;
+        for component in &mut self.description {
+            if let DescriptionComponent::KeyValue(ref key, ref mut value) = *component {
+                if !DATE_KEYS.contains(&key.as_str()) {
+                    continue;
+                }
+                match DateValue::parse(value) {
+                    Some(DateValue::Absolute(_)) | None => {},
+                    Some(date_value) => {
+                        *value = date_value.resolve(today).format("%Y-%m-%d").to_string();
+                        changed = true;
+                    },
+                }
+            }
+        }
+        changed
+    }
 }
 
 impl Display for Task {
@@ -349,4 +407,45 @@ mod tests {
         assert_eq!(uppercase.completed, false);
         assert_eq!(uppercase.description(), "X Some incomplete Task.");
     }
+
+    #[test]
+    fn new_task_should_parse_priority_and_dates() {
+        let task = Task::new("(A) 2017-11-24 Something to do.");
+        assert_eq!(task.priority, Some('A'));
+        assert_eq!(task.created_at(), Some(Utc.ymd(2017, 11, 24)));
+        assert_eq!(task.completed_at(), None);
+        assert_eq!(task.description(), "Something to do.");
+
+        let completed = Task::new("x (B) 2017-11-25 2017-11-24 Something done.");
+        assert!(completed.completed);
+        assert_eq!(completed.priority, Some('B'));
+        assert_eq!(completed.completed_at(), Some(Utc.ymd(2017, 11, 25)));
+        assert_eq!(completed.created_at(), Some(Utc.ymd(2017, 11, 24)));
+        assert_eq!(completed.description(), "Something done.");
+
+        let completed_on = Task::new("x 2017-11-25 Something done.");
+        assert_eq!(completed_on.completed_at(), Some(Utc.ymd(2017, 11, 25)));
+        assert_eq!(completed_on.created_at(), None);
+        assert_eq!(completed_on.to_string(), "x 2017-11-25 Something done.");
+
+        let second_date_in_description = Task::new("2017-11-24 2017-11-25 Something to do.");
+        assert_eq!(second_date_in_description.created_at(), Some(Utc.ymd(2017, 11, 24)));
+        assert_eq!(second_date_in_description.description(), "2017-11-25 Something to do.");
+    }
+
+    #[test]
+    fn new_task_should_round_trip() {
+        for line in &["x (B) 2017-11-25 2017-11-24 So many things to do.", "(A) 2017-11-24 Call +mom @phone", "x 2017-11-24 Done."] {
+            assert_eq!(Task::new(line).to_string(), *line);
+        }
+    }
+
+    #[test]
+    fn normalize_dates_resolves_relative_dates() {
+        let mut task = Task::new("(A) Pay rent due:eom t:fri +home after:tomorrow");
+
+        assert!(task.normalize_dates(Utc.ymd(2018, 11, 24)));
+        assert_eq!(task.to_string(), "(A) Pay rent due:2018-11-30 t:2018-11-30 +home after:tomorrow");
+        assert!(!task.normalize_dates(Utc.ymd(2018, 11, 24)));
+    }
 }
@@ -0,0 +1,745 @@
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Error;
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use chrono::prelude::*;
+use journal::Journal;
+use journal::Operation;
+use identity::TaskIdentity;
+use index::TaskIndex;
+use line::Line;
+use line::DEFAULT_COMMENT_PREFIX;
+use task::Task;
+
+/// The lines of a single todo.txt file. Line numbers start at 1 and count comments
+/// and blank lines too, just like in todo.sh.
+///
+/// Every change is recorded in the list's journal, so it can be undone and redone.
+/// An index over projects, contexts and options can be kept up to date with the list.
+#[derive(Debug, Clone)]
+pub struct TaskList {
+    path: PathBuf,
+    lines: Vec<Line>,
+    comment_prefix: Option<String>,
+    journal: Journal,
+    index: Option<TaskIndex<usize>>,
+    //the file content as of the last load or save and the operations applied since then,
+    //with whether they appended a task, as appends go to the end of a changed file
+    saved: String,
+    unsaved: Vec<(Operation, bool)>,
+}
+
+/// The result of `TaskList::sync`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SyncReport {
+    /// Whether the file was changed by someone else since it was loaded or saved.
+    pub changed_externally: bool,
+    /// Unsaved operations that couldn't be applied to the changed file, e.g. edits
+    /// of tasks that were removed in the meantime. They are dropped.
+    pub conflicts: Vec<Operation>,
+}
+
+impl TaskList {
+    pub fn new<P: AsRef<Path>>(path: P) -> TaskList {
+        TaskList {
+            path: path.as_ref().to_path_buf(),
+            lines: Vec::new(),
+            comment_prefix: Some(String::from(DEFAULT_COMMENT_PREFIX)),
+            journal: Journal::new(),
+            index: None,
+            saved: String::new(),
+            unsaved: Vec::new(),
+        }
+    }
+
+    pub fn parse<P: AsRef<Path>>(path: P, content: &str) -> TaskList {
+        let mut list = TaskList::new(path);
+        list.lines = content.lines().map(|line| Line::parse(line, Some(DEFAULT_COMMENT_PREFIX))).collect();
+        list
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<TaskList> {
+        let content = fs::read_to_string(path.as_ref())?;
+        let mut list = TaskList::parse(path, &content);
+        list.saved = content;
+        Ok(list)
+    }
+
+    /// Loads the list together with its journal file, see `Journal::sidecar_path`.
+    /// The journal is written whenever the list is saved, so undo and redo work across restarts.
+    pub fn load_with_journal<P: AsRef<Path>>(path: P) -> io::Result<TaskList> {
+        let mut list = TaskList::load(path.as_ref())?;
+        list.journal = Journal::load(Journal::sidecar_path(path.as_ref()))?;
+        Ok(list)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn comment_prefix(&self) -> Option<&str> {
+        self.comment_prefix.as_deref()
+    }
+
+    /// Changes the prefix of comment lines and classifies all lines again.
+    /// Without a prefix every non blank line is a task.
+    pub fn set_comment_prefix(&mut self, comment_prefix : Option<&str>) {
+        self.lines = self.lines.iter().map(|line| Line::parse(&line.to_string(), comment_prefix)).collect();
+        self.comment_prefix = comment_prefix.map(String::from);
+        if self.index.is_some() {
+            self.build_index();
+        }
+    }
+
+    /// Indexes the tasks of the list. From now on the index is updated whenever the list changes.
+    pub fn build_index(&mut self) -> &TaskIndex<usize> {
+        let index = TaskIndex::build(self.tasks());
+        self.index.insert(index)
+    }
+
+    /// The index of the list, line numbers are the task handles. `None` until `build_index` is called.
+    pub fn index(&self) -> Option<&TaskIndex<usize>> {
+        self.index.as_ref()
+    }
+
+    pub fn lines(&self) -> &[Line] {
+        &self.lines
+    }
+
+    /// The tasks of the list with their line numbers.
+    pub fn tasks(&self) -> impl Iterator<Item = (usize, &Task)> + '_ {
+        self.lines.iter().enumerate().filter_map(|(index, line)| line.task().map(|task| (index + 1, task)))
+    }
+
+    /// The number of lines, including comments and blank lines.
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// The task at the given line, `None` for comments and blank lines.
+    pub fn get(&self, line : usize) -> Option<&Task> {
+        match line {
+            0 => None,
+            line => self.lines.get(line - 1)?.task(),
+        }
+    }
+
+    /// The title of the section a line belongs to, which is the text of the
+    /// closest comment above it, e.g. `Sprint 42` for `# Sprint 42`.
+    pub fn section(&self, line : usize) -> Option<&str> {
+        let prefix = self.comment_prefix.as_ref()?;
+        self.lines.iter().take(line.saturating_sub(1)).rev()
+            .filter_map(|line| line.comment_text(prefix))
+            .next()
+    }
+
+    /// The tasks with their line numbers and the titles of their sections, see `section`.
+    pub fn sectioned_tasks(&self) -> impl Iterator<Item = (usize, Option<&str>, &Task)> {
+        let prefix = self.comment_prefix.as_deref();
+        let mut section = None;
+        //one pass, the current section changes with every header
+        self.lines.iter().enumerate().filter_map(move |(index, line)| {
+            if let Some(text) = prefix.and_then(|prefix| line.comment_text(prefix)) {
+                section = Some(text);
+                return None;
+            }
+            line.task().map(|task| (index + 1, section, task))
+        })
+    }
+
+    /// The tasks under a section header, with their line numbers.
+    pub fn section_tasks<'a>(&'a self, title : &'a str) -> impl Iterator<Item = (usize, &'a Task)> + 'a {
+        self.sectioned_tasks()
+            .filter(move |&(_, section, _)| section == Some(title))
+            .map(|(line, _, task)| (line, task))
+    }
+
+    pub fn journal(&self) -> &Journal {
+        &self.journal
+    }
+
+    /// The applied operations, oldest first.
+    pub fn history(&self) -> &[Operation] {
+        self.journal.history()
+    }
+
+    /// Appends a task and returns its line number. Relative dates are resolved against today.
+    pub fn add(&mut self, mut task : Task) -> usize {
+        task.normalize_dates(Utc::today());
+        let line = self.lines.len() + 1;
+        self.record(Operation::Add { line, task: task.to_string() });
+        self.insert_line(line, Line::Task(task));
+        line
+    }
+
+    /// Removes the task at the given line, the following tasks move up by one line.
+    pub fn remove(&mut self, line : usize) -> Option<Task> {
+        let task = self.get(line)?.to_string();
+        self.record(Operation::Remove { line, task });
+        match self.remove_line(line) {
+            Line::Task(task) => Some(task),
+            _ => None,
+        }
+    }
+
+    /// Replaces the task at the given line with a blank line, so unlike `remove` the
+    /// following tasks keep their line numbers. This is how `todo.sh del` deletes tasks.
+    pub fn clear(&mut self, line : usize) -> Option<Task> {
+        let old = self.get(line)?.to_string();
+        self.record(Operation::Replace { line, old, new: String::new() });
+        match self.set_line(line, Line::Blank(String::new())) {
+            Line::Task(task) => Some(task),
+            _ => None,
+        }
+    }
+
+    /// Removes all blank lines, e.g. the ones left behind by `clear`. Returns how many were removed.
+    pub fn remove_blank_lines(&mut self) -> usize {
+        let blank_lines = self.lines.iter().enumerate()
+            .filter(|&(_, line)| matches!(line, Line::Blank(_)))
+            .map(|(index, _)| index + 1)
+            .collect::<Vec<usize>>();
+        for &line in blank_lines.iter().rev() {
+            self.record(Operation::Remove { line, task: String::new() });
+            self.remove_line(line);
+        }
+        blank_lines.len()
+    }
+
+    /// Replaces the task at the given line and returns the old one. Relative dates
+    /// in the new task are resolved against today.
+    pub fn replace(&mut self, line : usize, mut task : Task) -> Option<Task> {
+        task.normalize_dates(Utc::today());
+        let old = self.get(line)?.to_string();
+        self.record(Operation::Replace { line, old, new: task.to_string() });
+        match self.set_line(line, Line::Task(task)) {
+            Line::Task(task) => Some(task),
+            _ => None,
+        }
+    }
+
+    /// Marks the task at the given line as completed, see `Task::complete`.
+    pub fn complete(&mut self, line : usize, date : Date<Utc>) -> Option<&Task> {
+        let mut task = self.get(line)?.clone();
+        let old = task.to_string();
+        task.complete(date);
+        self.record(Operation::Complete { line, old, new: task.to_string() });
+        self.set_line(line, Line::Task(task));
+        self.get(line)
+    }
+
+    pub fn set_priority(&mut self, line : usize, priority : Option<char>) -> Option<&Task> {
+        let mut task = self.get(line)?.clone();
+        let old = task.to_string();
+        task.priority = priority;
+        self.record(Operation::Reprioritize { line, old, new: task.to_string() });
+        self.set_line(line, Line::Task(task));
+        self.get(line)
+    }
+
+    /// Reverts up to `count` operations and returns how many were reverted. Stops
+    /// early if the list was changed in a way the journal can't follow.
+    pub fn undo(&mut self, count : usize) -> usize {
+        for undone in 0..count {
+            match self.journal.undo_operation() {
+                Some(ref operation) if self.apply(operation) => {
+                    self.journal.step_back();
+                    self.unsaved.push((operation.clone(), false));
+                },
+                _ => return undone,
+            }
+        }
+        count
+    }
+
+    /// Applies up to `count` undone operations again and returns how many were applied.
+    pub fn redo(&mut self, count : usize) -> usize {
+        for redone in 0..count {
+            match self.journal.redo_operation() {
+                Some(ref operation) if self.apply(operation) => {
+                    self.journal.step_forward();
+                    self.unsaved.push((operation.clone(), false));
+                },
+                _ => return redone,
+            }
+        }
+        count
+    }
+
+    /// Rewrites relative dates of all tasks to ISO dates, see `Task::normalize_dates`.
+    /// Returns the number of changed tasks, every change is recorded in the journal.
+    pub fn normalize_dates(&mut self, today : Date<Utc>) -> usize {
+        let changed = self.tasks()
+            .filter_map(|(line, task)| {
+                let mut normalized = task.clone();
+                if normalized.normalize_dates(today) { Some((line, normalized)) } else { None }
+            })
+            .collect::<Vec<(usize, Task)>>();
+        for (line, task) in &changed {
+            let old = self.get(*line).map(|old| old.to_string()).unwrap_or_default();
+            self.record(Operation::Replace { line: *line, old, new: task.to_string() });
+            self.set_line(*line, Line::Task(task.clone()));
+        }
+        changed.len()
+    }
+
+    /// Writes the list back to its file, with relative dates resolved against today so
+    /// the file only holds ISO dates. The content is written to a temporary file first
+    /// and then renamed, so readers never see a half written list.
+    pub fn save(&mut self) -> io::Result<()> {
+        self.normalize_dates(Utc::today());
+        let content = self.to_string();
+        write_atomically(&self.path, &content)?;
+        self.saved = content;
+        self.unsaved.clear();
+        self.journal.save()
+    }
+
+    /// Whether there are changes that haven't been saved yet.
+    pub fn is_modified(&self) -> bool {
+        !self.unsaved.is_empty()
+    }
+
+    /// Picks up changes other programs made to the file since it was loaded or saved.
+    ///
+    /// The unsaved changes of this list are applied again on top of the changed file.
+    /// Like undo, they follow tasks that moved to other lines. Changes to tasks that
+    /// are gone or were edited differently are conflicts and dropped.
+    pub fn merge_external_changes(&mut self) -> io::Result<SyncReport> {
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(ref error) if error.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(error) => return Err(error),
+        };
+        if content == self.saved {
+            return Ok(SyncReport::default());
+        }
+
+        let mut merged = TaskList::parse(&self.path, &content);
+        merged.set_comment_prefix(self.comment_prefix.as_deref());
+        let mut conflicts = Vec::new();
+        let mut unsaved = Vec::new();
+        for (operation, append) in self.unsaved.drain(..) {
+            let replayed = match operation {
+                Operation::Add { ref task, .. } if append => Operation::Add { line: merged.lines.len() + 1, task: task.clone() },
+                ref operation => operation.clone(),
+            };
+            if merged.apply(&replayed) {
+                unsaved.push((operation, append));
+            } else {
+                conflicts.push(operation);
+            }
+        }
+        self.unsaved = unsaved;
+        self.lines = merged.lines;
+        self.saved = content;
+        if self.index.is_some() {
+            self.build_index();
+        }
+        Ok(SyncReport { changed_externally: true, conflicts })
+    }
+
+    /// Saves the list without overwriting changes other programs made to the file
+    /// in the meantime, see `merge_external_changes`.
+    pub fn sync(&mut self) -> io::Result<SyncReport> {
+        let report = self.merge_external_changes()?;
+        if self.is_modified() || report.changed_externally {
+            self.save()?;
+        }
+        Ok(report)
+    }
+
+    //called before the operation is applied
+    fn record(&mut self, operation : Operation) {
+        let append = matches!(operation, Operation::Add { line, .. } if line == self.lines.len() + 1);
+        self.unsaved.push((operation.clone(), append));
+        self.journal.record(operation);
+    }
+
+    fn apply(&mut self, operation : &Operation) -> bool {
+        match operation {
+            Operation::Add { line, task } => {
+                let line = (*line).min(self.lines.len() + 1);
+                self.insert_line(line, journaled_line(task));
+                true
+            },
+            Operation::Remove { line, task } => match self.find_line(*line, task, false) {
+                Some(line) => {
+                    self.remove_line(line);
+                    true
+                },
+                None => false,
+            },
+            Operation::Replace { line, old, new }
+                | Operation::Complete { line, old, new }
+                | Operation::Reprioritize { line, old, new } => match self.find_line(*line, old, true) {
+                    Some(line) => {
+                        self.set_line(line, journaled_line(new));
+                        true
+                    },
+                    None => false,
+                },
+        }
+    }
+
+    //all changes of lines go through these, so the index stays up to date
+    fn insert_line(&mut self, line : usize, new_line : Line) {
+        if let Some(ref mut index) = self.index {
+            index.shift_lines(line, true);
+            if let Line::Task(ref task) = new_line {
+                index.insert(line, task);
+            }
+        }
+        self.lines.insert(line - 1, new_line);
+    }
+
+    fn remove_line(&mut self, line : usize) -> Line {
+        if let Some(ref mut index) = self.index {
+            index.remove(&line);
+            index.shift_lines(line, false);
+        }
+        self.lines.remove(line - 1)
+    }
+
+    fn set_line(&mut self, line : usize, new_line : Line) -> Line {
+        if let Some(ref mut index) = self.index {
+            match new_line {
+                Line::Task(ref task) => index.insert(line, task),
+                _ => index.remove(&line),
+            }
+        }
+        ::std::mem::replace(&mut self.lines[line - 1], new_line)
+    }
+
+    /// The line of a task the journal refers to. If the file was edited since the
+    /// operation was recorded, the task is looked up by its text at other lines and,
+    /// with `similar`, by its identity. Removals don't look for similar tasks, as they
+    /// would drop a task that isn't the journaled one.
+    fn find_line(&self, line : usize, task : &str, similar : bool) -> Option<usize> {
+        if task.is_empty() {
+            return match self.lines.get(line.wrapping_sub(1)) {
+                Some(Line::Blank(_)) => Some(line),
+                _ => None,
+            };
+        }
+        match self.get(line) {
+            Some(found) if found.to_string() == task => Some(line),
+            _ => self.tasks().find(|&(_, found)| found.to_string() == task).map(|(line, _)| line)
+                .or_else(|| if similar { TaskIdentity::of(&Task::new(task)).locate(self) } else { None }),
+        }
+    }
+}
+
+impl Display for TaskList {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
+        for line in &self.lines {
+            writeln!(formatter, "{}", line)?
+        }
+        Ok(())
+    }
+}
+
+//the journal records cleared lines as empty tasks
+fn journaled_line(task : &str) -> Line {
+    if task.is_empty() {
+        Line::Blank(String::new())
+    } else {
+        Line::Task(Task::new(task))
+    }
+}
+
+pub fn write_atomically(path : &Path, content : &str) -> io::Result<()> {
+    let file_name = path.file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Path doesn't point to a file."))?;
+    let mut temporary_name = ::std::ffi::OsString::from(".");
+    temporary_name.push(file_name);
+    temporary_name.push(".tmp");
+    let temporary_path = path.with_file_name(temporary_name);
+
+    {
+        let mut file = fs::File::create(&temporary_path)?;
+        file.write_all(content.as_bytes())?;
+        file.sync_all()?;
+    }
+
+    fs::rename(&temporary_path, path)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use task_list::*;
+    use parsers::DateValue;
+    use index::IndexTerm;
+    use std::env;
+    use std::process;
+
+    pub fn test_directory(name : &str) -> PathBuf {
+        let directory = env::temp_dir().join(format!("rustodo-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).expect("Must create test directory.");
+        directory
+    }
+
+    #[test]
+    fn parse_creates_one_task_per_line() {
+        let list = TaskList::parse("todo.txt", "(A) First\nx Second\n");
+
+        assert_eq!(list.len(), 2);
+        assert_eq!(list.get(1).expect("Must exist").priority, Some('A'));
+        assert!(list.get(2).expect("Must exist").completed);
+        assert!(list.get(0).is_none());
+        assert!(list.get(3).is_none());
+    }
+
+    #[test]
+    fn tasks_can_be_added_removed_and_replaced() {
+        let mut list = TaskList::parse("todo.txt", "First\nSecond\n");
+
+        assert_eq!(list.add(Task::new("Third")), 3);
+        assert_eq!(list.remove(1).expect("Must exist").description(), "First");
+        assert_eq!(list.replace(1, Task::new("New second")).expect("Must exist").description(), "Second");
+        assert_eq!(list.to_string(), "New second\nThird\n");
+
+        assert!(list.remove(5).is_none());
+        assert!(list.replace(0, Task::new("Nothing")).is_none());
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let path = test_directory("task-list-round-trip").join("todo.txt");
+        let content = "x 2018-11-25 2018-11-24 Done +project\n(B) Call mom @phone\n";
+
+        TaskList::parse(&path, content).save().expect("Must save.");
+        let list = TaskList::load(&path).expect("Must load.");
+
+        assert_eq!(list.to_string(), content);
+        assert_eq!(list.path(), path.as_path());
+    }
+
+    #[test]
+    fn complete_and_set_priority_change_the_task() {
+        let mut list = TaskList::parse("todo.txt", "(A) 2018-11-24 Write report\nCall mom\n");
+
+        list.complete(1, Utc.ymd(2018, 11, 25));
+        list.set_priority(2, Some('B'));
+
+        assert_eq!(list.to_string(), "x 2018-11-25 2018-11-24 Write report\n(B) Call mom\n");
+        assert!(list.complete(3, Utc.ymd(2018, 11, 25)).is_none());
+
+        let completed = list.complete(2, Utc.ymd(2018, 11, 26)).expect("Must complete.");
+        assert_eq!(completed.completed_at(), Some(Utc.ymd(2018, 11, 26)));
+        assert_eq!(completed.to_string(), "x 2018-11-26 2018-11-26 Call mom");
+    }
+
+    #[test]
+    fn undo_and_redo_revert_operations() {
+        let original = "(A) Write report\nCall mom\n";
+        let mut list = TaskList::parse("todo.txt", original);
+
+        list.add(Task::new("Buy milk"));
+        list.set_priority(1, None);
+        list.remove(2);
+        list.replace(2, Task::new("Buy oat milk"));
+        let edited = list.to_string();
+        assert_eq!(edited, "Write report\nBuy oat milk\n");
+        assert_eq!(list.history().len(), 4);
+
+        assert_eq!(list.undo(10), 4);
+        assert_eq!(list.to_string(), original);
+        assert_eq!(list.redo(10), 4);
+        assert_eq!(list.to_string(), edited);
+
+        assert_eq!(list.undo(1), 1);
+        list.add(Task::new("Something new"));
+        assert_eq!(list.redo(1), 0);
+    }
+
+    #[test]
+    fn cleared_lines_keep_the_line_numbers() {
+        let mut list = TaskList::parse("todo.txt", "Call mom\nWrite report\n");
+
+        assert_eq!(list.clear(1).map(|task| task.to_string()), Some(String::from("Call mom")));
+        assert_eq!(list.to_string(), "\nWrite report\n");
+        assert_eq!(list.get(2).map(|task| task.to_string()), Some(String::from("Write report")));
+        assert_eq!(list.undo(1), 1);
+        assert_eq!(list.to_string(), "Call mom\nWrite report\n");
+        assert_eq!(list.redo(1), 1);
+        assert_eq!(list.to_string(), "\nWrite report\n");
+
+        assert_eq!(list.remove_blank_lines(), 1);
+        assert_eq!(list.to_string(), "Write report\n");
+        assert_eq!(list.undo(1), 1);
+        assert_eq!(list.to_string(), "\nWrite report\n");
+    }
+
+    #[test]
+    fn sync_merges_external_changes() {
+        let path = test_directory("task-list-sync").join("todo.txt");
+        fs::write(&path, "Call mom\nWrite report\nPay rent\n").expect("Must write.");
+        let mut list = TaskList::load(&path).expect("Must load.");
+
+        list.set_priority(2, Some('A'));
+        list.replace(3, Task::new("Pay rent +home"));
+        fs::write(&path, "Added elsewhere\nCall mom\nWrite report\n").expect("Must write.");
+
+        let report = list.sync().expect("Must sync.");
+        assert!(report.changed_externally);
+        assert_eq!(report.conflicts, vec![Operation::Replace { line: 3, old: String::from("Pay rent"), new: String::from("Pay rent +home") }]);
+        assert_eq!(fs::read_to_string(&path).expect("Must read."), "Added elsewhere\nCall mom\n(A) Write report\n");
+        assert!(!list.is_modified());
+
+        assert_eq!(list.sync().expect("Must sync."), SyncReport::default());
+    }
+
+    #[test]
+    fn sync_keeps_appended_tasks_at_the_end() {
+        let path = test_directory("task-list-sync-append").join("todo.txt");
+        fs::write(&path, "Call mom\n").expect("Must write.");
+        let mut list = TaskList::load(&path).expect("Must load.");
+
+        list.add(Task::new("Buy milk"));
+        fs::write(&path, "Call mom\nAdded elsewhere\nAnd another one\n").expect("Must write.");
+
+        assert!(list.sync().expect("Must sync.").conflicts.is_empty());
+        assert_eq!(fs::read_to_string(&path).expect("Must read."), "Call mom\nAdded elsewhere\nAnd another one\nBuy milk\n");
+    }
+
+    #[test]
+    fn relative_dates_are_normalized() {
+        let mut list = TaskList::parse("todo.txt", "Pay rent due:eom\nCall mom\n");
+
+        assert_eq!(list.normalize_dates(Utc.ymd(2018, 11, 24)), 1);
+        assert_eq!(list.to_string(), "Pay rent due:2018-11-30\nCall mom\n");
+        assert_eq!(list.undo(1), 1);
+        assert_eq!(list.to_string(), "Pay rent due:eom\nCall mom\n");
+
+        let line = list.add(Task::new("Buy milk due:tomorrow"));
+        let tomorrow = Utc::today().succ().format("%Y-%m-%d").to_string();
+        assert_eq!(list.get(line).map(|task| task.options()["due"].clone()), Some(tomorrow));
+    }
+
+    #[test]
+    fn save_resolves_relative_dates_but_not_numbers() {
+        let path = test_directory("task-list-save-dates").join("todo.txt");
+        let content = "Pay rent due:5\nFile taxes due:2018\nCall mom due:fri\n";
+
+        let mut list = TaskList::parse(&path, content);
+        list.save().expect("Must save.");
+
+        let friday = DateValue::parse("fri").expect("Must parse.").resolve(Utc::today()).format("%F");
+        assert_eq!(TaskList::load(&path).expect("Must load.").to_string(),
+            format!("Pay rent due:5\nFile taxes due:2018\nCall mom due:{}\n", friday));
+        assert_eq!(list.history().len(), 1);
+    }
+
+    #[test]
+    fn undo_follows_moved_tasks() {
+        let mut list = TaskList::parse("todo.txt", "Call mom\nWrite report\n");
+        list.set_priority(2, Some('A'));
+        list.lines.insert(0, Line::Task(Task::new("Added by another client")));
+
+        assert_eq!(list.undo(1), 1);
+        assert_eq!(list.to_string(), "Added by another client\nCall mom\nWrite report\n");
+    }
+
+    #[test]
+    fn undo_only_removes_the_journaled_task() {
+        let mut list = TaskList::parse("todo.txt", "Call mom\n");
+        list.add(Task::new("Buy milk +home"));
+        list.lines[1] = Line::Task(Task::new("Buy milk +home @work"));
+
+        assert_eq!(list.undo(1), 0);
+        assert_eq!(list.to_string(), "Call mom\nBuy milk +home @work\n");
+    }
+
+    #[test]
+    fn journal_is_saved_and_loaded_with_the_list() {
+        let path = test_directory("task-list-journal").join("todo.txt");
+        fs::write(&path, "Call mom\n").expect("Must write.");
+
+        let mut list = TaskList::load_with_journal(&path).expect("Must load.");
+        list.set_priority(1, Some('A'));
+        list.save().expect("Must save.");
+
+        let mut reloaded = TaskList::load_with_journal(&path).expect("Must load.");
+        assert_eq!(reloaded.history().len(), 1);
+        assert_eq!(reloaded.undo(1), 1);
+        assert_eq!(reloaded.to_string(), "Call mom\n");
+    }
+
+    #[test]
+    fn comments_and_blank_lines_are_preserved() {
+        let content = "# Sprint 42\n(A) Write report\n\n# Sprint 43\nCall mom\n";
+        let list = TaskList::parse("todo.txt", content);
+
+        assert_eq!(list.len(), 5);
+        assert_eq!(list.tasks().map(|(line, _)| line).collect::<Vec<usize>>(), vec![2, 5]);
+        assert!(list.get(1).is_none());
+        assert!(list.get(3).is_none());
+        assert_eq!(list.to_string(), content);
+
+        let content = "Call mom\n  \t\nPay rent\n";
+        let list = TaskList::parse("todo.txt", content);
+        assert!(list.get(2).is_none());
+        assert_eq!(list.to_string(), content);
+    }
+
+    #[test]
+    fn tasks_belong_to_the_section_above_them() {
+        let list = TaskList::parse("todo.txt", "Inbox task\n# Sprint 42\n(A) Write report\n\nReview PR\n# Sprint 43\nCall mom\n");
+
+        assert_eq!(list.section(1), None);
+        assert_eq!(list.section(3), Some("Sprint 42"));
+        assert_eq!(list.section(5), Some("Sprint 42"));
+        assert_eq!(list.section(7), Some("Sprint 43"));
+        assert_eq!(list.section_tasks("Sprint 42").map(|(line, _)| line).collect::<Vec<usize>>(), vec![3, 5]);
+        assert_eq!(list.section_tasks("Sprint 43").map(|(line, _)| line).collect::<Vec<usize>>(), vec![7]);
+        assert_eq!(list.section_tasks("Sprint 44").count(), 0);
+    }
+
+    #[test]
+    fn comment_prefix_can_be_changed() {
+        let mut list = TaskList::parse("todo.txt", "# Not a header\n// Header\n");
+        list.set_comment_prefix(Some("//"));
+
+        assert!(list.get(1).is_some());
+        assert!(list.get(2).is_none());
+        assert_eq!(list.section(3), Some("Header"));
+
+        list.set_comment_prefix(None);
+        assert!(list.get(2).is_some());
+    }
+
+    #[test]
+    fn index_follows_changes_of_the_list() {
+        let mut list = TaskList::parse("todo.txt", "# Work\nWrite docs +rustodo\nCall mom +family\n");
+        let rustodo = IndexTerm::Project(String::from("rustodo"));
+        let family = IndexTerm::Project(String::from("family"));
+        let lines = |list : &TaskList, term : &IndexTerm| list.index().expect("Must be indexed").all(::std::slice::from_ref(term)).into_iter().collect::<Vec<usize>>();
+
+        assert!(list.index().is_none());
+        list.build_index();
+        assert_eq!(lines(&list, &rustodo), vec![2]);
+
+        list.add(Task::new("Fix bug +rustodo"));
+        list.remove(2);
+        assert_eq!(lines(&list, &rustodo), vec![3]);
+        assert_eq!(lines(&list, &family), vec![2]);
+
+        list.replace(2, Task::new("Call mom +rustodo"));
+        assert_eq!(lines(&list, &rustodo), vec![2, 3]);
+        assert!(list.index().expect("Must be indexed").get(&family).is_none());
+
+        list.undo(3);
+        assert_eq!(lines(&list, &rustodo), vec![2]);
+        assert_eq!(lines(&list, &family), vec![3]);
+    }
+}
@@ -0,0 +1,218 @@
+use chrono::prelude::*;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Error;
+use std::fs;
+use std::io;
+use std::path::Path;
+use toml;
+use group::group_by;
+use group::GroupKey;
+use group::Groups;
+use group::UnknownGroupKey;
+use query::Query;
+use query::QueryError;
+use sort::SortSpec;
+use sort::UnknownSortKey;
+use task::Task;
+use task_list::TaskList;
+
+/// A named perspective on tasks: a filter, an order and a grouping, each optional.
+///
+/// Views are defined in the `views` table of a TOML config, so every frontend
+/// shares the same definitions:
+///
+/// ```toml
+/// [views.today]
+/// query = "due<=today and not done"
+/// sort = "pri,due"
+/// group = "project"
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct View {
+    pub name: String,
+    pub query: Option<Query>,
+    pub sort: Option<SortSpec>,
+    pub group: Option<GroupKey>,
+}
+
+/// The tasks of a view, grouped if the view has a grouping.
+#[derive(Debug, Clone)]
+pub enum ViewResult<'a, T> {
+    List(Vec<(T, &'a Task)>),
+    Groups(Groups<'a, T>),
+}
+
+#[derive(Debug)]
+pub enum ViewError {
+    Io(io::Error),
+    Toml(toml::de::Error),
+    Query(String, QueryError),
+    Sort(String, UnknownSortKey),
+    Group(String, UnknownGroupKey),
+}
+
+impl Display for ViewError {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
+        match self {
+            ViewError::Io(error) => write!(formatter, "Couldn't read the views: {}", error),
+            ViewError::Toml(error) => write!(formatter, "Couldn't parse the views: {}", error),
+            ViewError::Query(view, error) => write!(formatter, "Invalid query in view '{}': {}", view, error),
+            ViewError::Sort(view, error) => write!(formatter, "Invalid sort in view '{}': {}", view, error),
+            ViewError::Group(view, error) => write!(formatter, "Invalid group in view '{}': {}", view, error),
+        }
+    }
+}
+
+impl From<io::Error> for ViewError {
+    fn from(error : io::Error) -> ViewError {
+        ViewError::Io(error)
+    }
+}
+
+impl From<toml::de::Error> for ViewError {
+    fn from(error : toml::de::Error) -> ViewError {
+        ViewError::Toml(error)
+    }
+}
+
+#[derive(Deserialize)]
+struct ViewsConfig {
+    #[serde(default)]
+    views: BTreeMap<String, ViewConfig>,
+}
+
+#[derive(Deserialize)]
+struct ViewConfig {
+    query: Option<String>,
+    sort: Option<String>,
+    group: Option<String>,
+}
+
+impl View {
+    pub fn new(name : &str, query : Option<&str>, sort : Option<&str>, group : Option<&str>) -> Result<View, ViewError> {
+        Ok(View {
+            name: String::from(name),
+            query: match query {
+                Some(query) => Some(Query::parse(query).map_err(|error| ViewError::Query(String::from(name), error))?),
+                None => None,
+            },
+            sort: match sort {
+                Some(sort) => Some(SortSpec::parse(sort).map_err(|error| ViewError::Sort(String::from(name), error))?),
+                None => None,
+            },
+            group: match group {
+                Some(group) => Some(GroupKey::parse(group).map_err(|error| ViewError::Group(String::from(name), error))?),
+                None => None,
+            },
+        })
+    }
+
+    /// The views of a TOML config, ordered by name. Other tables of the config are ignored.
+    pub fn parse_config(input : &str) -> Result<Vec<View>, ViewError> {
+        let config : ViewsConfig = toml::from_str(input)?;
+        config.views.iter()
+            .map(|(name, view)| View::new(name, view.query.as_deref(), view.sort.as_deref(), view.group.as_deref()))
+            .collect()
+    }
+
+    pub fn load_config<P: AsRef<Path>>(path : P) -> Result<Vec<View>, ViewError> {
+        View::parse_config(&fs::read_to_string(path)?)
+    }
+
+    /// Filters and sorts tasks that come with a handle like their line number, see `group_by`.
+    /// Without a sort the tasks keep their order. The tasks aren't in any section.
+    pub fn evaluate<'a, T, I>(&self, tasks : I, today : Date<Utc>) -> ViewResult<'a, T>
+        where T: Clone, I: IntoIterator<Item = (T, &'a Task)> {
+        self.evaluate_in_sections(tasks.into_iter().map(|(handle, task)| (handle, None, task)), today)
+    }
+
+    /// Like `evaluate` over the tasks of a list with their line numbers, queries can match their sections.
+    pub fn evaluate_list<'a>(&self, list : &'a TaskList, today : Date<Utc>) -> ViewResult<'a, usize> {
+        self.evaluate_in_sections(list.sectioned_tasks(), today)
+    }
+
+    fn evaluate_in_sections<'a, T, I>(&self, tasks : I, today : Date<Utc>) -> ViewResult<'a, T>
+        where T: Clone, I: IntoIterator<Item = (T, Option<&'a str>, &'a Task)> {
+        let mut tasks = tasks.into_iter()
+            .filter(|&(_, section, task)| self.query.as_ref().is_none_or(|query| query.matches_in_section(task, section, today)))
+            .map(|(handle, _, task)| (handle, task))
+            .collect::<Vec<(T, &Task)>>();
+        if let Some(ref sort) = self.sort {
+            tasks.sort_by(|left, right| sort.compare(left.1, right.1));
+        }
+        match self.group {
+            Some(ref key) => ViewResult::Groups(group_by(tasks, key)),
+            None => ViewResult::List(tasks),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use view::*;
+    use group::GroupName;
+    use task_list::TaskList;
+
+    const CONFIG : &str = r#"
+[views.today]
+query = "due<=today and not done"
+sort = "pri,due"
+group = "project"
+
+[views.all]
+"#;
+
+    #[test]
+    fn views_are_loaded_from_toml() {
+        let views = View::parse_config(CONFIG).expect("Must parse.");
+
+        assert_eq!(views.iter().map(|view| view.name.as_str()).collect::<Vec<&str>>(), vec!["all", "today"]);
+        assert_eq!(views[0], View { name: String::from("all"), query: None, sort: None, group: None });
+        assert_eq!(views[1].group, Some(GroupKey::Project));
+        assert_eq!(views[1].sort, SortSpec::parse("pri,due").ok());
+    }
+
+    #[test]
+    fn invalid_views_name_the_problem() {
+        let error = View::parse_config("[views.broken]\nquery = \"due<=\"").expect_err("Must not parse.");
+        assert!(error.to_string().starts_with("Invalid query in view 'broken':"));
+
+        let error = View::parse_config("[views.broken]\nsort = \"size\"").expect_err("Must not parse.");
+        assert_eq!(error.to_string(), "Invalid sort in view 'broken': Unknown sort key 'size'.");
+
+        let error = View::parse_config("[views.broken]\ngroup = \"projects\"").expect_err("Must not parse.");
+        assert_eq!(error.to_string(), "Invalid group in view 'broken': Unknown group key 'projects', options are grouped with 'key:NAME'.");
+    }
+
+    #[test]
+    fn views_filter_sort_and_group() {
+        let list = TaskList::parse("todo.txt", "Later due:2018-12-01 +home\n(B) Rent due:2018-11-24 +home\nx Done due:2018-11-20 +home\n(A) Bug due:2018-11-23 +work\n(A) Docs due:2018-11-24 +home\n");
+        let views = View::parse_config(CONFIG).expect("Must parse.");
+
+        let groups = match views[1].evaluate(list.tasks(), Utc.ymd(2018, 11, 24)) {
+            ViewResult::Groups(groups) => groups,
+            ViewResult::List(_) => panic!("The view is grouped."),
+        };
+        let home = groups[&GroupName::Value(String::from("home"))].iter().map(|&(line, _)| line).collect::<Vec<usize>>();
+        assert_eq!(home, vec![5, 2]);
+        assert_eq!(groups.len(), 2);
+
+        match views[0].evaluate(list.tasks(), Utc.ymd(2018, 11, 24)) {
+            ViewResult::List(tasks) => assert_eq!(tasks.len(), 5),
+            ViewResult::Groups(_) => panic!("The view isn't grouped."),
+        }
+    }
+
+    #[test]
+    fn list_views_can_filter_by_section() {
+        let list = TaskList::parse("todo.txt", "Inbox task\n# Sprint 42\n(A) Write report\n# Sprint 43\nCall mom\n");
+        let view = View::new("sprint", Some("section:\"Sprint 42\""), None, None).expect("Must parse.");
+
+        match view.evaluate_list(&list, Utc.ymd(2018, 11, 24)) {
+            ViewResult::List(tasks) => assert_eq!(tasks.iter().map(|&(line, _)| line).collect::<Vec<usize>>(), vec![3]),
+            ViewResult::Groups(_) => panic!("The view isn't grouped."),
+        }
+    }
+}
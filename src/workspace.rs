@@ -0,0 +1,220 @@
+use glob::glob;
+use std::env;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+use identity::TaskIdentity;
+use task::Task;
+use task_list::TaskList;
+
+/// Where a task lives: the file it was loaded from and its line number in that file.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Location {
+    pub path: PathBuf,
+    pub line: usize,
+}
+
+/// Several todo lists that are queried and edited together.
+#[derive(Debug, Clone, Default)]
+pub struct Workspace {
+    lists: Vec<TaskList>,
+}
+
+impl Workspace {
+    pub fn new() -> Workspace {
+        Workspace {
+            lists: Vec::new(),
+        }
+    }
+
+    /// Loads every file matching one of the glob patterns, e.g. `~/todo/*.txt`
+    /// or `~/src/*/TODO.txt`. Files matched by more than one pattern are only loaded once.
+    pub fn load(patterns : &[&str]) -> io::Result<Workspace> {
+        let mut workspace = Workspace::new();
+        for pattern in patterns {
+            let paths = glob(&expand_home(pattern))
+                .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error.to_string()))?;
+            for path in paths {
+                let path = path.map_err(io::Error::from)?;
+                if path.is_file() && workspace.list(&path).is_none() {
+                    workspace.add_list(TaskList::load(&path)?);
+                }
+            }
+        }
+        Ok(workspace)
+    }
+
+    pub fn add_list(&mut self, list : TaskList) {
+        self.lists.push(list)
+    }
+
+    pub fn lists(&self) -> &[TaskList] {
+        &self.lists
+    }
+
+    pub fn list(&self, path : &Path) -> Option<&TaskList> {
+        self.lists.iter().find(|list| list.path() == path)
+    }
+
+    pub fn get(&self, location : &Location) -> Option<&Task> {
+        self.list(&location.path)?.get(location.line)
+    }
+
+    /// Finds a task again, even if it was moved to another line or list of the workspace.
+    pub fn locate(&self, identity : &TaskIdentity) -> Option<Location> {
+        let mut best : Option<(Location, f64)> = None;
+        for list in &self.lists {
+            if let Some(line) = identity.locate(list) {
+                let similarity = identity.similarity(list.get(line)?);
+                if best.as_ref().is_none_or(|&(_, best_similarity)| similarity > best_similarity) {
+                    best = Some((Location { path: list.path().to_path_buf(), line }, similarity));
+                }
+            }
+        }
+        best.map(|(location, _)| location)
+    }
+
+    /// All tasks of all lists, in the order the lists were added.
+    pub fn tasks(&self) -> Vec<(Location, &Task)> {
+        self.filter(|_| true)
+    }
+
+    pub fn filter<P: Fn(&Task) -> bool>(&self, predicate : P) -> Vec<(Location, &Task)> {
+        self.lists.iter()
+            .flat_map(|list| list.tasks().map(move |(line, task)| (Location {
+                path: list.path().to_path_buf(),
+                line,
+            }, task)))
+            .filter(|&(_, task)| predicate(task))
+            .collect()
+    }
+
+    /// Moves a task to the end of another list of the workspace and returns its new location.
+    ///
+    /// Both files are written through temporary files. The destination is written
+    /// first, if writing the source fails afterwards the destination is restored,
+    /// so the task ends up in exactly one of the files.
+    pub fn move_task(&mut self, from : &Location, to : &Path) -> io::Result<Location> {
+        let source_index = self.index_of(&from.path)?;
+        let destination_index = self.index_of(to)?;
+        if source_index == destination_index {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "Source and destination are the same list."));
+        }
+
+        let mut source = self.lists[source_index].clone();
+        let task = source.remove(from.line)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("There is no task at {}:{}.", from.path.display(), from.line)))?;
+        let mut destination = self.lists[destination_index].clone();
+        let line = destination.add(task);
+
+        destination.save()?;
+        if let Err(error) = source.save() {
+            self.lists[destination_index].save()?;
+            return Err(error);
+        }
+
+        self.lists[source_index] = source;
+        self.lists[destination_index] = destination;
+        Ok(Location {
+            path: to.to_path_buf(),
+            line,
+        })
+    }
+
+    pub fn save(&mut self) -> io::Result<()> {
+        for list in &mut self.lists {
+            list.save()?
+        }
+        Ok(())
+    }
+
+    fn index_of(&self, path : &Path) -> io::Result<usize> {
+        self.lists.iter().position(|list| list.path() == path)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{} is not part of the workspace.", path.display())))
+    }
+}
+
+fn expand_home(pattern : &str) -> String {
+    match (pattern.starts_with("~/"), env::var("HOME")) {
+        (true, Ok(home)) => format!("{}{}", home, &pattern[1..]),
+        _ => pattern.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use workspace::*;
+    use task_list::tests::test_directory;
+    use std::fs;
+
+    fn workspace_fixture(name : &str) -> PathBuf {
+        let directory = test_directory(name);
+        fs::write(directory.join("work.txt"), "(A) Write report +work\nCall Bob @phone\n").expect("Must write.");
+        fs::write(directory.join("home.txt"), "Water plants @home\n").expect("Must write.");
+        fs::write(directory.join("notes.md"), "Not a todo list\n").expect("Must write.");
+        directory
+    }
+
+    #[test]
+    fn load_matches_globs_once() {
+        let directory = workspace_fixture("workspace-load");
+        let pattern = format!("{}/*.txt", directory.display());
+        let workspace = Workspace::load(&[&pattern, &pattern]).expect("Must load.");
+
+        assert_eq!(workspace.lists().len(), 2);
+        assert_eq!(workspace.tasks().len(), 3);
+    }
+
+    #[test]
+    fn tasks_remember_their_location() {
+        let directory = workspace_fixture("workspace-location");
+        let pattern = format!("{}/work.txt", directory.display());
+        let workspace = Workspace::load(&[&pattern]).expect("Must load.");
+
+        let phone_tasks = workspace.filter(|task| task.contexts().contains(&String::from("phone")));
+        assert_eq!(phone_tasks.len(), 1);
+        assert_eq!(phone_tasks[0].0, Location { path: directory.join("work.txt"), line: 2 });
+        assert_eq!(workspace.get(&phone_tasks[0].0).expect("Must exist").description(), "Call Bob @phone");
+    }
+
+    #[test]
+    fn move_task_writes_both_files() {
+        let directory = workspace_fixture("workspace-move");
+        let pattern = format!("{}/*.txt", directory.display());
+        let mut workspace = Workspace::load(&[&pattern]).expect("Must load.");
+
+        let from = Location { path: directory.join("work.txt"), line: 2 };
+        let to = workspace.move_task(&from, &directory.join("home.txt")).expect("Must move.");
+
+        assert_eq!(to, Location { path: directory.join("home.txt"), line: 2 });
+        assert_eq!(fs::read_to_string(directory.join("work.txt")).expect("Must read."), "(A) Write report +work\n");
+        assert_eq!(fs::read_to_string(directory.join("home.txt")).expect("Must read."), "Water plants @home\nCall Bob @phone\n");
+    }
+
+    #[test]
+    fn locate_finds_moved_tasks() {
+        let directory = workspace_fixture("workspace-locate");
+        let pattern = format!("{}/*.txt", directory.display());
+        let mut workspace = Workspace::load(&[&pattern]).expect("Must load.");
+
+        let from = Location { path: directory.join("work.txt"), line: 2 };
+        let identity = TaskIdentity::of(workspace.get(&from).expect("Must exist"));
+        let to = workspace.move_task(&from, &directory.join("home.txt")).expect("Must move.");
+
+        assert_eq!(workspace.locate(&identity), Some(to));
+    }
+
+    #[test]
+    fn move_task_fails_for_unknown_lines_and_lists() {
+        let directory = workspace_fixture("workspace-move-errors");
+        let pattern = format!("{}/*.txt", directory.display());
+        let mut workspace = Workspace::load(&[&pattern]).expect("Must load.");
+
+        let missing_line = Location { path: directory.join("work.txt"), line: 42 };
+        assert!(workspace.move_task(&missing_line, &directory.join("home.txt")).is_err());
+
+        let existing_line = Location { path: directory.join("work.txt"), line: 1 };
+        assert!(workspace.move_task(&existing_line, &directory.join("notes.md")).is_err());
+        assert_eq!(workspace.list(&directory.join("work.txt")).expect("Must exist").len(), 2);
+    }
+}